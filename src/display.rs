@@ -1,9 +1,14 @@
-use crate::report::parsed::{ArrayValueItem, Field, ParsedReport, VarItem};
+use crate::report::diff::{DescriptorDiff, FieldChange, FieldKey, ReportChange};
+use crate::report::parsed::{ArrayNoUsageItem, ArrayValueItem, Field, ParsedReport, VarItem};
+use crate::report::transport::{LengthViolation, TransportProfile};
+use crate::report::ReportType;
 use crate::report_descriptor::data::{Size, SizedPayload};
+use crate::report_descriptor::parse::Diagnostic;
+use crate::report_descriptor::unit::{Unit, UnitSystem};
 use crate::report_descriptor::{
-    Collection, Data, DataFieldOptions, GlobalType, ItemType, Linear, LocalType, MainType,
-    Mutability, NullState, ReportDescriptor, ReportDescriptorItem, State, Structure, Value,
-    Volatile, Wrap,
+    Collection, Data, DataFieldOptions, GlobalType, ItemType, Linear, Lint, LocalType, MainType,
+    Mutability, NullState, ReportDescriptor, ReportDescriptorItem, Rule, Severity, State,
+    Structure, Value, Volatile, Wrap,
 };
 use crate::usage_table::consumer::ConsumerUsage;
 use crate::usage_table::fido::FIDOAllianceUsage;
@@ -29,6 +34,7 @@ impl fmt::Display for GlobalType {
             GlobalType::ReportCount => f.write_str("Report Count"),
             GlobalType::Push => f.write_str("Push"),
             GlobalType::Pop => f.write_str("Pop"),
+            GlobalType::Reserved(tag) => write!(f, "Reserved Global Item ({:#x})", tag),
         }
     }
 }
@@ -41,6 +47,7 @@ impl fmt::Display for MainType {
             MainType::Feature => f.write_str("Feature"),
             MainType::Collection => f.write_str("Collection"),
             MainType::EndCollection => f.write_str("End Collection"),
+            MainType::Reserved(tag) => write!(f, "Reserved Main Item ({:#x})", tag),
         }
     }
 }
@@ -167,6 +174,7 @@ impl fmt::Display for LocalType {
             LocalType::StringMinimum => f.write_str("String Minimum"),
             LocalType::StringMaximum => f.write_str("String Maximum"),
             LocalType::Delimiter => f.write_str("Delimiter"),
+            LocalType::Reserved(tag) => write!(f, "Reserved Local Item ({:#x})", tag),
         }
     }
 }
@@ -221,6 +229,8 @@ impl fmt::Display for ItemType {
             ItemType::Main(item) => write!(f, "{}", item),
             ItemType::Global(item) => write!(f, "{}", item),
             ItemType::Local(item) => write!(f, "{}", item),
+            ItemType::Long(tag) => write!(f, "Long Item ({:#04x})", tag),
+            ItemType::Reserved(tag) => write!(f, "Reserved Item ({:#x})", tag),
         }
     }
 }
@@ -252,20 +262,111 @@ impl fmt::Display for ReportDescriptorItem {
             };
         }
 
+        if self.is_unit() {
+            return match self.unit() {
+                Some(unit) if unit.is_none() => write!(f, "{} (None)", self.kind),
+                Some(unit) => write!(f, "{} ({}, {})", self.kind, unit.system(), unit),
+                None => write!(f, "{} (!!ERROR UNKNOWN!!)", self.kind),
+            };
+        }
+
         match self.payload_size {
             Size::Empty => write!(f, "{}", self.kind),
+            Size::Long(_) => write!(f, "{} {}", self.kind, self.raw_payload()),
             _ => write!(f, "{} ({})", self.kind, self.raw_payload()),
         }
     }
 }
 
+impl fmt::Display for UnitSystem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitSystem::None => f.write_str("None"),
+            UnitSystem::SILinear => f.write_str("SI Linear"),
+            UnitSystem::SIRotation => f.write_str("SI Rotation"),
+            UnitSystem::EnglishLinear => f.write_str("English Linear"),
+            UnitSystem::EnglishRotation => f.write_str("English Rotation"),
+            UnitSystem::Reserved(i) => write!(f, "Reserved ({})", i),
+            UnitSystem::VendorDefined => f.write_str("Vendor Defined"),
+        }
+    }
+}
+
+/// Implement Display for Unit
+///
+/// Every base unit with a non-zero exponent is displayed with its symbol and a
+/// superscript exponent, for example `cm·s⁻²`. Systems without defined base units
+/// display the name of the quantity instead, for example `Length·Time⁻¹`.
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_none() {
+            return f.write_str("None");
+        }
+
+        let symbols = match self.system() {
+            UnitSystem::SILinear => ["cm", "g", "s", "K", "A", "cd"],
+            UnitSystem::SIRotation => ["rad", "g", "s", "K", "A", "cd"],
+            UnitSystem::EnglishLinear => ["in", "slug", "s", "°F", "A", "cd"],
+            UnitSystem::EnglishRotation => ["deg", "slug", "s", "°F", "A", "cd"],
+            _ => [
+                "Length",
+                "Mass",
+                "Time",
+                "Temperature",
+                "Current",
+                "Luminous Intensity",
+            ],
+        };
+
+        let mut first = true;
+        for (symbol, exponent) in symbols.iter().zip(self.exponents()) {
+            if exponent == 0 {
+                continue;
+            }
+
+            if !first {
+                f.write_str("·")?;
+            }
+            first = false;
+
+            f.write_str(symbol)?;
+            if exponent != 1 {
+                for c in exponent.to_string().chars() {
+                    f.write_str(superscript(c))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Superscript variant of a digit or minus sign
+fn superscript(c: char) -> &'static str {
+    match c {
+        '-' => "⁻",
+        '0' => "⁰",
+        '1' => "¹",
+        '2' => "²",
+        '3' => "³",
+        '4' => "⁴",
+        '5' => "⁵",
+        '6' => "⁶",
+        '7' => "⁷",
+        '8' => "⁸",
+        _ => "",
+    }
+}
+
 /// Implement Display for Payload
 ///
-/// Will display the output as i32
+/// Will display the output as i32, or as a list of bytes for long items
 impl fmt::Display for SizedPayload {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        if self.size() != Size::Empty {
-            write!(f, "{}", i32::from(self.clone()))?;
+        match self {
+            SizedPayload::Empty => {}
+            SizedPayload::Long(data) => write!(f, "{:02x?}", data)?,
+            _ => write!(f, "{}", i32::from(self.clone()))?,
         }
 
         Ok(())
@@ -298,7 +399,7 @@ impl fmt::Display for ReportDescriptor {
                 "{:02x?}{:>width$}",
                 item.raw,
                 "",
-                width = 20 - len * 2 - 2 - 2 * (len - 1)
+                width = 20usize.saturating_sub(len * 4)
             )?;
 
             // Display indentation
@@ -309,9 +410,14 @@ impl fmt::Display for ReportDescriptor {
 
             // Display item
             if item.is_usage() || item.is_usage_minimum() || item.is_usage_maximum() {
-                let usage = Usage::try_from((&usage_page, item.payload_u16().unwrap_or(0)))
-                    .unwrap_or_default();
-                writeln!(f, "{} ({})", item.kind, usage)?;
+                let (page, id) = item
+                    .usage_page_and_id(&usage_page)
+                    .unwrap_or((usage_page.clone(), 0));
+                let usage = Usage::from((&page, id));
+                match item.is_extended_usage() {
+                    true => writeln!(f, "{} ({} - {})", item.kind, page, usage)?,
+                    false => writeln!(f, "{} ({})", item.kind, usage)?,
+                }
             } else {
                 writeln!(f, "{}", item)?;
             }
@@ -1315,13 +1421,21 @@ impl fmt::Display for Field {
             Field::ArrayZeroValue(_) => {
                 write!(f, "")
             }
+            Field::ArrayNoUsage(item) => write!(f, "{}", item),
         }
     }
 }
 
 impl fmt::Display for VarItem {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{} - {}({})", self.usage_page, self.usage, self.value)
+        match self.out_of_range {
+            true => write!(
+                f,
+                "{} - {}({}, out of range)",
+                self.usage_page, self.usage, self.value
+            ),
+            false => write!(f, "{} - {}({})", self.usage_page, self.usage, self.value),
+        }
     }
 }
 
@@ -1331,6 +1445,199 @@ impl fmt::Display for ArrayValueItem {
     }
 }
 
+impl fmt::Display for ArrayNoUsageItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - No usage({})", self.usage_page, self.value)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] item {}: {}",
+            self.severity(),
+            self.rule(),
+            self.index(),
+            self.explanation()
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::ReservedTag { offset, prefix } => {
+                write!(f, "reserved item tag {:#04x} at offset {}", prefix, offset)
+            }
+            Diagnostic::Truncated(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl fmt::Display for ReportType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportType::Input => write!(f, "Input"),
+            ReportType::Output => write!(f, "Output"),
+            ReportType::Feature => write!(f, "Feature"),
+        }
+    }
+}
+
+/// Write the type and Report ID of a report, e.g. "Input report 1"
+fn report_name(f: &mut Formatter<'_>, report_type: &ReportType, id: &Option<u8>) -> fmt::Result {
+    match id {
+        Some(id) => write!(f, "{} report {}", report_type, id),
+        None => write!(f, "{} report", report_type),
+    }
+}
+
+impl fmt::Display for DescriptorDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for change in self.changes() {
+            match change {
+                ReportChange::Added {
+                    report_type,
+                    report_id,
+                    size,
+                } => {
+                    report_name(f, report_type, report_id)?;
+                    writeln!(f, ": added ({} bits)", size)?;
+                }
+                ReportChange::Removed {
+                    report_type,
+                    report_id,
+                    size,
+                } => {
+                    report_name(f, report_type, report_id)?;
+                    writeln!(f, ": removed ({} bits)", size)?;
+                }
+                ReportChange::Changed {
+                    report_type,
+                    report_id,
+                    changes,
+                } => {
+                    for change in changes {
+                        report_name(f, report_type, report_id)?;
+                        writeln!(f, ": {}", change)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for TransportProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportProfile::UsbFullSpeed => f.write_str("USB full-speed"),
+            TransportProfile::UsbHighSpeed => f.write_str("USB high-speed"),
+            TransportProfile::BleHogp { mtu } => write!(f, "BLE HOGP (MTU {})", mtu),
+            TransportProfile::I2cHid { .. } => f.write_str("I2C-HID"),
+        }
+    }
+}
+
+impl fmt::Display for LengthViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        report_name(f, &self.report().report_type(), &self.report().report_id())?;
+        write!(
+            f,
+            " is {} bytes, the limit of {} is {} bytes",
+            self.transferred(),
+            self.profile(),
+            self.limit()
+        )
+    }
+}
+
+impl fmt::Display for FieldKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldKey::Variable(page, usage, 0) => write!(f, "{} - {}", page, usage),
+            FieldKey::Variable(page, usage, n) => write!(f, "{} - {} #{}", page, usage, n + 1),
+            FieldKey::Array(page, n) => write!(f, "{} array slot {}", page, n + 1),
+            FieldKey::Padding(n) => write!(f, "padding {}", n + 1),
+        }
+    }
+}
+
+/// Write a minimum and maximum as "min..max", "?" for a bound that is not declared
+fn range(f: &mut Formatter<'_>, (minimum, maximum): &(Option<i64>, Option<i64>)) -> fmt::Result {
+    let bound = |b: &Option<i64>| b.map_or(String::from("?"), |b| b.to_string());
+    write!(f, "{}..{}", bound(minimum), bound(maximum))
+}
+
+/// Write a unit with its exponent, e.g. "cm (exponent -2)"
+fn unit(f: &mut Formatter<'_>, (unit, exponent): &(Option<Unit>, Option<i32>)) -> fmt::Result {
+    match unit {
+        Some(unit) => write!(f, "{}", unit)?,
+        None => write!(f, "None")?,
+    }
+    match exponent {
+        Some(exponent) if *exponent != 0 => write!(f, " (exponent {})", exponent),
+        _ => Ok(()),
+    }
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldChange::ReportSize { old, new } => {
+                write!(f, "size changed from {} to {} bits", old, new)
+            }
+            FieldChange::Added(field) => write!(f, "added {}", field),
+            FieldChange::Removed(field) => write!(f, "removed {}", field),
+            FieldChange::Moved { field, old, new } => {
+                write!(f, "{} moved from bit {} to bit {}", field, old, new)
+            }
+            FieldChange::Size { field, old, new } => {
+                write!(f, "{} size changed from {} to {} bits", field, old, new)
+            }
+            FieldChange::LogicalRange { field, old, new } => {
+                write!(f, "{} logical range changed from ", field)?;
+                range(f, old)?;
+                write!(f, " to ")?;
+                range(f, new)
+            }
+            FieldChange::PhysicalRange { field, old, new } => {
+                write!(f, "{} physical range changed from ", field)?;
+                range(f, old)?;
+                write!(f, " to ")?;
+                range(f, new)
+            }
+            FieldChange::Unit { field, old, new } => {
+                write!(f, "{} unit changed from ", field)?;
+                unit(f, old)?;
+                write!(f, " to ")?;
+                unit(f, new)
+            }
+            FieldChange::Options { field, old, new } => {
+                write!(f, "{} changed from {} to {}", field, old, new)
+            }
+            FieldChange::ArrayUsages { field, .. } => write!(f, "{} usages changed", field),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1353,4 +1660,63 @@ mod tests {
         let p = SizedPayload::from(bytes);
         assert_eq!(format!("{}", p), "17");
     }
+
+    #[test]
+    fn unit_item() {
+        let item = ReportDescriptorItem {
+            kind: ItemType::Global(GlobalType::Unit),
+            payload_size: Size::Two,
+            raw: vec![0x66, 0x11, 0xe0],
+        };
+
+        assert_eq!(format!("{}", item), "Unit (SI Linear, cm·s⁻²)");
+    }
+
+    #[test]
+    fn unit_none_item() {
+        let item = ReportDescriptorItem {
+            kind: ItemType::Global(GlobalType::Unit),
+            payload_size: Size::One,
+            raw: vec![0x65, 0x00],
+        };
+
+        assert_eq!(format!("{}", item), "Unit (None)");
+    }
+
+    #[test]
+    fn unit_english_rotation() {
+        let unit = Unit::new(UnitSystem::EnglishRotation)
+            .with_length(1)
+            .with_time(-1);
+        assert_eq!(format!("{}", unit), "deg·s⁻¹");
+    }
+
+    #[test]
+    fn unit_vendor_defined() {
+        let unit = Unit::new(UnitSystem::VendorDefined).with_mass(-8);
+        assert_eq!(format!("{}", unit), "Mass⁻⁸");
+    }
+
+    #[test]
+    fn extended_usage_in_descriptor() {
+        let descriptor = crate::report_builder::ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::X)
+            .extended_usage(UsagePage::Consumer, ConsumerUsage::ACPan)
+            .build();
+        let output = format!("{}", descriptor);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines[1].ends_with("Usage (X)"));
+        assert!(lines[2].ends_with("Usage (Consumer - AC Pan)"));
+    }
+
+    #[test]
+    fn long_item() {
+        let item =
+            ReportDescriptorItem::try_from((ItemType::Long(0xf1), SizedPayload::Long(vec![1, 2])))
+                .unwrap();
+
+        assert_eq!(format!("{}", item), "Long Item (0xf1) [01, 02]");
+    }
 }
@@ -1,5 +1,48 @@
-#[derive(Debug, PartialEq)]
+pub use crate::report::{InputError, ParseError};
+pub use crate::report_builder::BuildError;
+pub use crate::report_descriptor::parse::DescriptorError;
+pub use crate::report_descriptor::ItemError;
+
+/// Any error of this crate
+///
+/// Every stage of the pipeline has its own error type: [`DescriptorError`] for parsing
+/// a Report Descriptor, [`ItemError`] and [`BuildError`] for building one and
+/// [`InputError`] for deriving the report layout and decoding reports. All of them convert into this type, so `?`
+/// works across the whole pipeline.
+///
+/// # Example
+/// ```
+/// use hid_tools::error::Error;
+/// use hid_tools::report::{expected_input_reports, parse_raw_input_report, parsed::ParsedReport};
+/// use hid_tools::report_descriptor::parse::report_descriptor;
+///
+/// fn decode(descriptor: &[u8], report: &[u8]) -> Result<ParsedReport, Error> {
+///     let descriptor = report_descriptor(descriptor)?;
+///     let expected = expected_input_reports(&descriptor)?;
+///     Ok(parse_raw_input_report(report, &expected)?)
+/// }
+///
+/// assert!(matches!(decode(&[0x05], &[]), Err(Error::Descriptor(_))));
+/// ```
+#[derive(Debug, PartialEq, thiserror::Error)]
 pub enum Error {
-    ParsingFailed(String),
-    PayloadEmpty,
+    /// The Report Descriptor could not be parsed
+    #[error("invalid report descriptor: {0}")]
+    Descriptor(#[from] DescriptorError),
+
+    /// An item could not be encoded
+    #[error("invalid report descriptor item: {0}")]
+    Item(#[from] ItemError),
+
+    /// The Report Descriptor could not be built
+    #[error("cannot build report descriptor: {0}")]
+    Build(#[from] BuildError),
+
+    /// The report layout could not be derived, or a report could not be decoded
+    #[error("cannot decode report: {0}")]
+    Report(#[from] InputError),
+
+    /// A transformed Report Descriptor does not describe the same reports as the original
+    #[error("report descriptors are not equivalent")]
+    NotEquivalent,
 }
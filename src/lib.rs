@@ -116,7 +116,6 @@
 //!
 //! ## Needs some work
 //!
-//! - Logical minimum/maximum
 //! - Convert more Usage tables (help wanted)
 //!
 //!
@@ -131,7 +130,7 @@
 mod display;
 
 /// Errors
-mod error;
+pub mod error;
 
 /// Create a list of expected reports from a Report Descriptor and parse event reports
 pub mod report;
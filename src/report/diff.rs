@@ -0,0 +1,492 @@
+use crate::report::expected::{ExpectedField, ExpectedFieldItem, ExpectedReport, UsageRange};
+use crate::report::{expected_reports, InputError, ReportType};
+use crate::report_descriptor::unit::Unit;
+use crate::report_descriptor::{DataFieldOptions, ReportDescriptor};
+use crate::usage_table::{Usage, UsagePage};
+
+/// The semantic differences between two Report Descriptors
+///
+/// Reports are matched by type and Report ID, fields within a report by their usage
+/// (and occurrence), so inserting a field shows up as one added field and moved
+/// fields, not as a change of every following byte.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DescriptorDiff {
+    changes: Vec<ReportChange>,
+}
+
+/// A change of one report
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReportChange {
+    /// The report only exists in the new descriptor
+    Added {
+        /// Type of the report
+        report_type: ReportType,
+        /// Report ID of the report
+        report_id: Option<u8>,
+        /// Size of the report in bits
+        size: usize,
+    },
+
+    /// The report only exists in the old descriptor
+    Removed {
+        /// Type of the report
+        report_type: ReportType,
+        /// Report ID of the report
+        report_id: Option<u8>,
+        /// Size of the report in bits
+        size: usize,
+    },
+
+    /// The report exists in both descriptors, with a different layout
+    Changed {
+        /// Type of the report
+        report_type: ReportType,
+        /// Report ID of the report
+        report_id: Option<u8>,
+        /// The changes within the report
+        changes: Vec<FieldChange>,
+    },
+}
+
+/// Identifies a field of a report independent of its position
+///
+/// The last value counts earlier fields with the same identity, for example the
+/// second slot of a keyboard array.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FieldKey {
+    /// A variable field with its Usage Page and usage
+    Variable(UsagePage, Usage, usize),
+
+    /// An array slot on a Usage Page
+    Array(UsagePage, usize),
+
+    /// Constant (padding) bits
+    Padding(usize),
+}
+
+/// A change within a report
+#[derive(Debug, PartialEq, Clone)]
+pub enum FieldChange {
+    /// The size of the report in bits changed
+    ReportSize {
+        /// Size in the old descriptor
+        old: usize,
+        /// Size in the new descriptor
+        new: usize,
+    },
+
+    /// The field only exists in the new descriptor
+    Added(FieldKey),
+
+    /// The field only exists in the old descriptor
+    Removed(FieldKey),
+
+    /// The bit index of the field in the report changed
+    Moved {
+        /// The field
+        field: FieldKey,
+        /// Bit index in the old descriptor
+        old: usize,
+        /// Bit index in the new descriptor
+        new: usize,
+    },
+
+    /// The size of the field in bits changed
+    Size {
+        /// The field
+        field: FieldKey,
+        /// Size in the old descriptor
+        old: usize,
+        /// Size in the new descriptor
+        new: usize,
+    },
+
+    /// The Logical Minimum or Logical Maximum changed
+    LogicalRange {
+        /// The field
+        field: FieldKey,
+        /// Minimum and maximum in the old descriptor
+        old: (Option<i64>, Option<i64>),
+        /// Minimum and maximum in the new descriptor
+        new: (Option<i64>, Option<i64>),
+    },
+
+    /// The Physical Minimum or Physical Maximum changed
+    PhysicalRange {
+        /// The field
+        field: FieldKey,
+        /// Minimum and maximum in the old descriptor
+        old: (Option<i64>, Option<i64>),
+        /// Minimum and maximum in the new descriptor
+        new: (Option<i64>, Option<i64>),
+    },
+
+    /// The Unit or Unit Exponent changed
+    Unit {
+        /// The field
+        field: FieldKey,
+        /// Unit and exponent in the old descriptor
+        old: (Option<Unit>, Option<i32>),
+        /// Unit and exponent in the new descriptor
+        new: (Option<Unit>, Option<i32>),
+    },
+
+    /// The data field options of the main item changed
+    Options {
+        /// The field
+        field: FieldKey,
+        /// Options in the old descriptor
+        old: DataFieldOptions,
+        /// Options in the new descriptor
+        new: DataFieldOptions,
+    },
+
+    /// The usages an array slot can select changed
+    ArrayUsages {
+        /// The field
+        field: FieldKey,
+        /// Usages in the old descriptor
+        old: Vec<UsageRange>,
+        /// Usages in the new descriptor
+        new: Vec<UsageRange>,
+    },
+}
+
+/// Compare the Input, Output and Feature reports of two Report Descriptors
+///
+/// # Example
+/// ```
+/// use hid_tools::report::diff::descriptor_diff;
+/// use hid_tools::report_builder::ReportDescriptorBuilder;
+/// use hid_tools::usage_table::UsagePage;
+/// use hid_tools::usage_table::generic_desktop::GenericDesktopControlsUsage;
+///
+/// let old = ReportDescriptorBuilder::new()
+///     .usage_page(UsagePage::GenericDesktopControls)
+///     .usage(GenericDesktopControlsUsage::X)
+///     .logical_minimum(-127)
+///     .logical_maximum(127)
+///     .report_size(8)
+///     .report_count(1)
+///     .input(0x06)
+///     .build();
+///
+/// let new = ReportDescriptorBuilder::new()
+///     .usage_page(UsagePage::GenericDesktopControls)
+///     .usage(GenericDesktopControlsUsage::X)
+///     .logical_minimum(-2047)
+///     .logical_maximum(2047)
+///     .report_size(12)
+///     .report_count(1)
+///     .input(0x06)
+///     .build();
+///
+/// let diff = descriptor_diff(&old, &new).unwrap();
+/// assert_eq!(
+///     diff.to_string(),
+///     "Input report: size changed from 8 to 12 bits
+/// Input report: Generic Desktop Controls - X size changed from 8 to 12 bits
+/// Input report: Generic Desktop Controls - X logical range changed from -127..127 to -2047..2047
+/// "
+/// );
+/// ```
+pub fn descriptor_diff(
+    old: &ReportDescriptor,
+    new: &ReportDescriptor,
+) -> Result<DescriptorDiff, InputError> {
+    let mut changes: Vec<ReportChange> = Vec::new();
+
+    for report_type in [ReportType::Input, ReportType::Output, ReportType::Feature] {
+        let old_reports = expected_reports(old, report_type)?;
+        let new_reports = expected_reports(new, report_type)?;
+
+        for old_report in old_reports.reports() {
+            match new_reports.find_report(old_report.report_id()) {
+                Some(new_report) => {
+                    let field_changes = report_diff(old_report, new_report);
+                    if !field_changes.is_empty() {
+                        changes.push(ReportChange::Changed {
+                            report_type,
+                            report_id: old_report.report_id(),
+                            changes: field_changes,
+                        });
+                    }
+                }
+                None => changes.push(ReportChange::Removed {
+                    report_type,
+                    report_id: old_report.report_id(),
+                    size: old_report.size(),
+                }),
+            }
+        }
+
+        for new_report in new_reports.reports() {
+            if old_reports.find_report(new_report.report_id()).is_none() {
+                changes.push(ReportChange::Added {
+                    report_type,
+                    report_id: new_report.report_id(),
+                    size: new_report.size(),
+                });
+            }
+        }
+    }
+
+    Ok(DescriptorDiff { changes })
+}
+
+impl DescriptorDiff {
+    /// Get the changed reports, Input reports first, then Output and Feature reports
+    pub fn changes(&self) -> &Vec<ReportChange> {
+        &self.changes
+    }
+
+    /// Returns true if both descriptors describe the same reports
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Compare the fields of two reports with the same type and Report ID
+fn report_diff(old: &ExpectedReport, new: &ExpectedReport) -> Vec<FieldChange> {
+    let mut changes: Vec<FieldChange> = Vec::new();
+
+    if old.size() != new.size() {
+        changes.push(FieldChange::ReportSize {
+            old: old.size(),
+            new: new.size(),
+        });
+    }
+
+    let old_fields = keyed_fields(old);
+    let new_fields = keyed_fields(new);
+
+    for (key, old_item) in &old_fields {
+        match new_fields.iter().find(|(k, _)| k == key) {
+            Some((_, new_item)) => field_diff(key, old_item, new_item, &mut changes),
+            None => changes.push(FieldChange::Removed(key.clone())),
+        }
+    }
+
+    for (key, _) in &new_fields {
+        if !old_fields.iter().any(|(k, _)| k == key) {
+            changes.push(FieldChange::Added(key.clone()));
+        }
+    }
+
+    changes
+}
+
+/// Compare the properties of a field that exists in both reports
+fn field_diff(
+    key: &FieldKey,
+    old: &ExpectedFieldItem,
+    new: &ExpectedFieldItem,
+    changes: &mut Vec<FieldChange>,
+) {
+    if old.index() != new.index() {
+        changes.push(FieldChange::Moved {
+            field: key.clone(),
+            old: old.index(),
+            new: new.index(),
+        });
+    }
+
+    if old.size() != new.size() {
+        changes.push(FieldChange::Size {
+            field: key.clone(),
+            old: old.size(),
+            new: new.size(),
+        });
+    }
+
+    let logical = |item: &ExpectedFieldItem| (item.logical_minimum(), item.logical_maximum());
+    if logical(old) != logical(new) {
+        changes.push(FieldChange::LogicalRange {
+            field: key.clone(),
+            old: logical(old),
+            new: logical(new),
+        });
+    }
+
+    let physical = |item: &ExpectedFieldItem| (item.physical_minimum(), item.physical_maximum());
+    if physical(old) != physical(new) {
+        changes.push(FieldChange::PhysicalRange {
+            field: key.clone(),
+            old: physical(old),
+            new: physical(new),
+        });
+    }
+
+    let unit = |item: &ExpectedFieldItem| (item.unit().cloned(), item.unit_exponent());
+    if unit(old) != unit(new) {
+        changes.push(FieldChange::Unit {
+            field: key.clone(),
+            old: unit(old),
+            new: unit(new),
+        });
+    }
+
+    if old.options() != new.options() {
+        changes.push(FieldChange::Options {
+            field: key.clone(),
+            old: old.options().clone(),
+            new: new.options().clone(),
+        });
+    }
+
+    if old.array_usages() != new.array_usages() {
+        changes.push(FieldChange::ArrayUsages {
+            field: key.clone(),
+            old: old.array_usages().clone(),
+            new: new.array_usages().clone(),
+        });
+    }
+}
+
+/// Give every data field of a report its key, skipping the Report ID
+fn keyed_fields(report: &ExpectedReport) -> Vec<(FieldKey, &ExpectedFieldItem)> {
+    let mut fields: Vec<(FieldKey, &ExpectedFieldItem)> = Vec::new();
+
+    for field in report.fields() {
+        let (key, item) = match field {
+            ExpectedField::ReportId(_) => continue,
+            ExpectedField::Variable(item) => (
+                FieldKey::Variable(item.usage_page().clone(), item.usage().clone(), 0),
+                item,
+            ),
+            ExpectedField::ArrayItem(item) => (FieldKey::Array(item.usage_page().clone(), 0), item),
+            ExpectedField::Constant(item) => (FieldKey::Padding(0), item),
+        };
+
+        let occurrence = fields
+            .iter()
+            .filter(|(k, _)| k.with_occurrence(0) == key)
+            .count();
+        fields.push((key.with_occurrence(occurrence), item));
+    }
+
+    fields
+}
+
+impl FieldKey {
+    /// Get the number of earlier fields with the same identity
+    pub fn occurrence(&self) -> usize {
+        match self {
+            FieldKey::Variable(_, _, n) | FieldKey::Array(_, n) | FieldKey::Padding(n) => *n,
+        }
+    }
+
+    fn with_occurrence(&self, occurrence: usize) -> FieldKey {
+        match self {
+            FieldKey::Variable(page, usage, _) => {
+                FieldKey::Variable(page.clone(), usage.clone(), occurrence)
+            }
+            FieldKey::Array(page, _) => FieldKey::Array(page.clone(), occurrence),
+            FieldKey::Padding(_) => FieldKey::Padding(occurrence),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report_builder::ReportDescriptorBuilder;
+    use crate::usage_table::generic_desktop::GenericDesktopControlsUsage;
+    use crate::usage_table::keyboard::KeyboardUsage;
+
+    fn mouse(wheel: bool) -> ReportDescriptor {
+        let builder = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .report_id(2)
+            .logical_minimum(-127)
+            .logical_maximum(127)
+            .report_size(8)
+            .report_count(2)
+            .usage(GenericDesktopControlsUsage::X)
+            .usage(GenericDesktopControlsUsage::Y)
+            .input(0x06);
+
+        match wheel {
+            true => builder
+                .report_count(1)
+                .usage(GenericDesktopControlsUsage::Wheel)
+                .input(0x06)
+                .build(),
+            false => builder.build(),
+        }
+    }
+
+    #[test]
+    fn identical_descriptors() {
+        let diff = descriptor_diff(&mouse(true), &mouse(true)).unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "");
+    }
+
+    #[test]
+    fn added_field() {
+        let diff = descriptor_diff(&mouse(false), &mouse(true)).unwrap();
+
+        assert_eq!(
+            diff.changes(),
+            &vec![ReportChange::Changed {
+                report_type: ReportType::Input,
+                report_id: Some(2),
+                changes: vec![
+                    FieldChange::ReportSize { old: 24, new: 32 },
+                    FieldChange::Added(FieldKey::Variable(
+                        UsagePage::GenericDesktopControls,
+                        Usage::GenericDesktopControls(GenericDesktopControlsUsage::Wheel),
+                        0
+                    )),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_reports() {
+        let keyboard = ReportDescriptorBuilder::new()
+            .report_id(1)
+            .usage_page(UsagePage::Keyboard)
+            .usage_minimum(KeyboardUsage::KeyboardLeftControl)
+            .usage_maximum(KeyboardUsage::KeyboardRightGUI)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .report_size(1)
+            .report_count(8)
+            .input(0x02)
+            .build();
+
+        let diff = descriptor_diff(&keyboard, &mouse(false)).unwrap();
+
+        assert_eq!(
+            diff.to_string(),
+            "Input report 1: removed (16 bits)\nInput report 2: added (24 bits)\n"
+        );
+    }
+
+    #[test]
+    fn array_slots_are_matched_by_occurrence() {
+        let keyboard = |count: u32| {
+            ReportDescriptorBuilder::new()
+                .usage_page(UsagePage::Keyboard)
+                .usage_minimum(0x00u16)
+                .usage_maximum(0x65u16)
+                .logical_minimum(0)
+                .logical_maximum(0x65)
+                .report_size(8)
+                .report_count(count)
+                .input(0x00)
+                .build()
+        };
+
+        let diff = descriptor_diff(&keyboard(6), &keyboard(5)).unwrap();
+
+        assert_eq!(
+            diff.to_string(),
+            "Input report: size changed from 48 to 40 bits\n\
+             Input report: removed Keyboard array slot 6\n"
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use crate::report_descriptor::unit::Unit;
 use crate::report_descriptor::DataFieldOptions;
 use crate::usage_table::{Usage, UsagePage};
+use std::collections::{BTreeMap, HashMap};
 
 /// From an HID Report Descriptor we can derive a list of expected reports. ExpectedReports
 /// describes these reports.
@@ -12,6 +14,7 @@ pub struct ExpectedReports {
     pub(super) reports: Vec<ExpectedReport>,
 }
 
+/// The layout of a single report: its Report ID, size in bits and data fields
 #[derive(Debug, PartialEq)]
 pub struct ExpectedReport {
     pub(super) report_id: Option<u8>,
@@ -19,21 +22,57 @@ pub struct ExpectedReport {
     pub(super) fields: Vec<ExpectedField>,
 }
 
+/// A data field in an expected report
 #[derive(Debug, PartialEq)]
 pub enum ExpectedField {
+    /// The Report ID prefix byte
     ReportId(u8),
+
+    /// Constant (padding) bits
     Constant(ExpectedFieldItem),
+
+    /// A variable value of one control
     Variable(ExpectedFieldItem),
+
+    /// One slot of an array, holding the index of an active control
     ArrayItem(ExpectedFieldItem),
 }
 
-#[derive(Debug, PartialEq)]
+/// Location and meaning of a data field in a report
+#[derive(Debug, PartialEq, Default)]
 pub struct ExpectedFieldItem {
     pub(super) usage_page: UsagePage, // From Report Descriptor
     pub(super) usage: Usage,          // From Report Descriptor
     pub(super) index_in_raw: usize,
     pub(super) size_bits: usize, // Report Size
     pub(super) options: DataFieldOptions,
+    pub(super) logical_minimum: Option<i64>,
+    pub(super) logical_maximum: Option<i64>,
+    pub(super) physical_minimum: Option<i64>,
+    pub(super) physical_maximum: Option<i64>,
+    pub(super) unit_exponent: Option<i32>,
+    pub(super) unit: Option<Unit>,
+    pub(super) array_usages: Vec<UsageRange>, // Usages selectable by an array item
+    pub(super) alternate_usages: Vec<Usage>,  // From a Delimiter set
+    pub(super) designator_index: Option<u32>,
+    pub(super) string_index: Option<u32>,
+}
+
+/// A table of strings to resolve String Index items against, for example the string
+/// descriptors read from the device
+pub trait StringTable {
+    /// Get the string with the given index, if it exists
+    fn string(&self, index: u32) -> Option<&str>;
+}
+
+/// An inclusive range of usages on one usage page
+///
+/// A single Usage item is a range where minimum and maximum are equal.
+#[derive(Debug, PartialEq, Clone)]
+pub struct UsageRange {
+    pub(super) usage_page: UsagePage,
+    pub(super) minimum: u16,
+    pub(super) maximum: u16,
 }
 
 impl ExpectedReports {
@@ -41,6 +80,264 @@ impl ExpectedReports {
     pub fn find_report(&self, report_id: Option<u8>) -> Option<&ExpectedReport> {
         self.reports.iter().find(|&r| r.report_id == report_id)
     }
+
+    /// Returns true if the reports are prefixed with a Report ID
+    pub fn has_report_id(&self) -> bool {
+        self.has_report_id
+    }
+
+    /// Get all expected reports
+    pub fn reports(&self) -> &Vec<ExpectedReport> {
+        &self.reports
+    }
+}
+
+impl ExpectedReport {
+    /// Get the Report ID, if any
+    pub fn report_id(&self) -> Option<u8> {
+        self.report_id
+    }
+
+    /// Get the total size of the report in bits, including the Report ID
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the fields of the report, in order of appearance
+    pub fn fields(&self) -> &Vec<ExpectedField> {
+        &self.fields
+    }
+}
+
+impl ExpectedFieldItem {
+    /// Get the Usage Page
+    pub fn usage_page(&self) -> &UsagePage {
+        &self.usage_page
+    }
+
+    /// Get the Usage
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// Get the alternate usages of the control, declared in a Delimiter set after
+    /// the primary usage
+    pub fn alternate_usages(&self) -> &Vec<Usage> {
+        &self.alternate_usages
+    }
+
+    /// Get the Designator Index of the control, pointing to the body part used
+    /// to operate it in the Physical Descriptor
+    pub fn designator_index(&self) -> Option<u32> {
+        self.designator_index
+    }
+
+    /// Get the String Index of the control
+    pub fn string_index(&self) -> Option<u32> {
+        self.string_index
+    }
+
+    /// Resolve the String Index against a string table, giving the vendor's own
+    /// name for the control
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hid_tools::report::expected_input_reports;
+    /// use hid_tools::report::expected::ExpectedField;
+    /// use hid_tools::report_builder::ReportDescriptorBuilder;
+    ///
+    /// let descriptor = ReportDescriptorBuilder::new()
+    ///     .usage_page(0x01u16)
+    ///     .usage(0x30u16)
+    ///     .string_index(4)
+    ///     .report_size(8)
+    ///     .report_count(1)
+    ///     .input(0x02)
+    ///     .build();
+    ///
+    /// let strings = HashMap::from([(4, String::from("Throttle"))]);
+    /// let expected = expected_input_reports(&descriptor).unwrap();
+    ///
+    /// match &expected.reports()[0].fields()[0] {
+    ///     ExpectedField::Variable(item) => assert_eq!(item.string(&strings), Some("Throttle")),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn string<'t, T: StringTable + ?Sized>(&self, table: &'t T) -> Option<&'t str> {
+        table.string(self.string_index?)
+    }
+
+    /// Get the bit index of the field in the raw report
+    pub fn index(&self) -> usize {
+        self.index_in_raw
+    }
+
+    /// Get the size of the field in bits
+    pub fn size(&self) -> usize {
+        self.size_bits
+    }
+
+    /// Get the data field options of the Input, Output or Feature item
+    pub fn options(&self) -> &DataFieldOptions {
+        &self.options
+    }
+
+    /// Get the Logical Minimum, if it was declared
+    pub fn logical_minimum(&self) -> Option<i64> {
+        self.logical_minimum
+    }
+
+    /// Get the Logical Maximum, if it was declared
+    pub fn logical_maximum(&self) -> Option<i64> {
+        self.logical_maximum
+    }
+
+    /// Get the Physical Minimum, if it was declared
+    pub fn physical_minimum(&self) -> Option<i64> {
+        self.physical_minimum
+    }
+
+    /// Get the Physical Maximum, if it was declared
+    pub fn physical_maximum(&self) -> Option<i64> {
+        self.physical_maximum
+    }
+
+    /// Get the Unit Exponent (base 10), if it was declared
+    pub fn unit_exponent(&self) -> Option<i32> {
+        self.unit_exponent
+    }
+
+    /// Get the Unit, if it was declared
+    pub fn unit(&self) -> Option<&Unit> {
+        self.unit.as_ref()
+    }
+
+    /// Convert a logical value to a physical value
+    ///
+    /// The logical range is linearly mapped onto the physical range and scaled with the
+    /// Unit Exponent. The result is expressed in the base units of the unit system, for
+    /// example centimetres for a length in SI Linear. When the physical range is not
+    /// declared (or both bounds are zero) it equals the logical range. The Unit Exponent
+    /// belongs to the Unit, so it is ignored when no Unit (or Unit None) is declared.
+    ///
+    /// For definition in the HID protocol see
+    /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 38
+    pub fn physical_value(&self, value: i64) -> f64 {
+        let exponent = match &self.unit {
+            Some(unit) if !unit.is_none() => 10f64.powi(self.unit_exponent.unwrap_or_default()),
+            _ => 1.0,
+        };
+
+        let physical = match (
+            self.logical_minimum,
+            self.logical_maximum,
+            self.physical_minimum.unwrap_or_default(),
+            self.physical_maximum.unwrap_or_default(),
+        ) {
+            (_, _, 0, 0) => value as f64,
+            (Some(logical_min), Some(logical_max), physical_min, physical_max)
+                if logical_min != logical_max =>
+            {
+                let resolution =
+                    (physical_max - physical_min) as f64 / (logical_max - logical_min) as f64;
+                physical_min as f64 + (value - logical_min) as f64 * resolution
+            }
+            _ => value as f64,
+        };
+
+        physical * exponent
+    }
+
+    /// Get the usages an array item can select, in order of their index
+    pub fn array_usages(&self) -> &Vec<UsageRange> {
+        &self.array_usages
+    }
+
+    /// Resolve the value of an array item to the usage page and usage ID it selects
+    ///
+    /// The value minus the Logical Minimum is the index in the declared usages. Returns
+    /// None if the value is outside the logical range or there is no usage at that index.
+    ///
+    /// For definition in the HID protocol see
+    /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 42
+    pub fn array_usage(&self, value: i64) -> Option<(UsagePage, u16)> {
+        if self.is_out_of_range(value) {
+            return None;
+        }
+
+        let mut index = u64::try_from(value - self.logical_minimum.unwrap_or_default()).ok()?;
+        for range in &self.array_usages {
+            let count = range.len();
+            if index < count {
+                return Some((range.usage_page.clone(), range.minimum + index as u16));
+            }
+            index -= count;
+        }
+
+        None
+    }
+
+    /// Returns true if values of this field are signed (two's complement)
+    ///
+    /// A field is signed when its Logical Minimum is negative.
+    pub fn is_signed(&self) -> bool {
+        self.logical_minimum.unwrap_or_default() < 0
+    }
+
+    /// Returns true if the value lies outside the declared logical range
+    ///
+    /// If no (valid) logical range was declared, every value is in range.
+    pub fn is_out_of_range(&self, value: i64) -> bool {
+        match (self.logical_minimum, self.logical_maximum) {
+            (Some(min), Some(max)) if min <= max => value < min || value > max,
+            _ => false,
+        }
+    }
+}
+
+impl StringTable for HashMap<u32, String> {
+    fn string(&self, index: u32) -> Option<&str> {
+        self.get(&index).map(String::as_str)
+    }
+}
+
+impl StringTable for BTreeMap<u32, String> {
+    fn string(&self, index: u32) -> Option<&str> {
+        self.get(&index).map(String::as_str)
+    }
+}
+
+impl UsageRange {
+    /// Get the Usage Page of the range
+    pub fn usage_page(&self) -> &UsagePage {
+        &self.usage_page
+    }
+
+    /// Get the first usage ID of the range
+    pub fn minimum(&self) -> u16 {
+        self.minimum
+    }
+
+    /// Get the last usage ID of the range
+    pub fn maximum(&self) -> u16 {
+        self.maximum
+    }
+
+    /// Number of usages in the range, zero if the maximum is below the minimum
+    pub(super) fn len(&self) -> u64 {
+        (u64::from(self.maximum) + 1).saturating_sub(u64::from(self.minimum))
+    }
+}
+
+impl From<(UsagePage, u16, u16)> for UsageRange {
+    fn from(value: (UsagePage, u16, u16)) -> Self {
+        UsageRange {
+            usage_page: value.0,
+            minimum: value.1,
+            maximum: value.2,
+        }
+    }
 }
 
 impl From<(bool, Vec<ExpectedReport>)> for ExpectedReports {
@@ -53,8 +350,8 @@ impl From<(bool, Vec<ExpectedReport>)> for ExpectedReports {
 }
 
 impl ExpectedField {
-    /// Return the size of the item
-    pub(super) fn size(&self) -> usize {
+    /// Return the size of the item in bits
+    pub fn size(&self) -> usize {
         match self {
             ExpectedField::ReportId(_) => 8,
             ExpectedField::Constant(item) => item.size_bits,
@@ -63,8 +360,8 @@ impl ExpectedField {
         }
     }
 
-    /// Get the index in the raw report
-    pub(super) fn index(&self) -> usize {
+    /// Get the bit index in the raw report
+    pub fn index(&self) -> usize {
         match self {
             ExpectedField::ReportId(_) => 0,
             ExpectedField::Constant(item) => item.index_in_raw,
@@ -72,12 +369,22 @@ impl ExpectedField {
             ExpectedField::ArrayItem(item) => item.index_in_raw,
         }
     }
+
+    /// Returns true if the raw value of the field is signed
+    pub fn is_signed(&self) -> bool {
+        match self {
+            ExpectedField::ReportId(_) => false,
+            ExpectedField::Constant(item) => item.is_signed(),
+            ExpectedField::Variable(item) => item.is_signed(),
+            ExpectedField::ArrayItem(item) => item.is_signed(),
+        }
+    }
 }
 
-impl<'a> TryFrom<Vec<super::input::Input<'a>>> for ExpectedReport {
+impl<'a> TryFrom<Vec<super::input::DataItem<'a>>> for ExpectedReport {
     type Error = super::InputError;
 
-    fn try_from(value: Vec<super::input::Input<'a>>) -> Result<Self, Self::Error> {
+    fn try_from(value: Vec<super::input::DataItem<'a>>) -> Result<Self, Self::Error> {
         if value.is_empty() {
             return Err(super::InputError::Empty);
         }
@@ -93,8 +400,8 @@ impl<'a> TryFrom<Vec<super::input::Input<'a>>> for ExpectedReport {
         }
 
         // Add other fields
-        for input in value {
-            let mut expected = super::input::expected_fields(input, size)?;
+        for data_item in value {
+            let mut expected = super::input::expected_fields(data_item, size)?;
             let total_size_of_expected: usize = expected.iter().map(|f| f.size()).sum();
             size += total_size_of_expected;
 
@@ -108,3 +415,90 @@ impl<'a> TryFrom<Vec<super::input::Input<'a>>> for ExpectedReport {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn physical_value_without_physical_range() {
+        let item = ExpectedFieldItem {
+            logical_minimum: Some(-127),
+            logical_maximum: Some(127),
+            ..Default::default()
+        };
+
+        assert_eq!(item.physical_value(-5), -5.0);
+    }
+
+    #[test]
+    fn physical_value_with_unit_exponent() {
+        // A digitizer axis: 0..=4095 maps to 0..=2000 with exponent -2 (0..=20.00 cm)
+        let item = ExpectedFieldItem {
+            logical_minimum: Some(0),
+            logical_maximum: Some(4095),
+            physical_minimum: Some(0),
+            physical_maximum: Some(2000),
+            unit_exponent: Some(-2),
+            unit: Some(Unit::from(0x11)),
+            ..Default::default()
+        };
+
+        assert_eq!(item.physical_value(0), 0.0);
+        assert!((item.physical_value(4095) - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn physical_value_ignores_exponent_without_unit() {
+        let item = ExpectedFieldItem {
+            logical_minimum: Some(0),
+            logical_maximum: Some(255),
+            unit_exponent: Some(-2),
+            ..Default::default()
+        };
+
+        assert_eq!(item.physical_value(100), 100.0);
+    }
+
+    #[test]
+    fn array_usage_through_logical_minimum() {
+        // Consumer control array: Logical Min 1 selects the first usage
+        let item = ExpectedFieldItem {
+            logical_minimum: Some(1),
+            logical_maximum: Some(3),
+            array_usages: vec![UsageRange::from((UsagePage::Consumer, 0xe9, 0xeb))],
+            ..Default::default()
+        };
+
+        assert_eq!(item.array_usage(0), None);
+        assert_eq!(item.array_usage(1), Some((UsagePage::Consumer, 0xe9)));
+        assert_eq!(item.array_usage(3), Some((UsagePage::Consumer, 0xeb)));
+        assert_eq!(item.array_usage(4), None);
+    }
+
+    #[test]
+    fn array_usage_continues_in_next_range() {
+        let item = ExpectedFieldItem {
+            array_usages: vec![
+                UsageRange::from((UsagePage::Consumer, 0xcd, 0xcd)),
+                UsageRange::from((UsagePage::Consumer, 0xe2, 0xe2)),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(item.array_usage(1), Some((UsagePage::Consumer, 0xe2)));
+        assert_eq!(item.array_usage(2), None);
+    }
+
+    #[test]
+    fn string_index_from_table() {
+        let item = ExpectedFieldItem {
+            string_index: Some(2),
+            ..Default::default()
+        };
+        let strings = BTreeMap::from([(1, String::from("Left")), (2, String::from("Right"))]);
+
+        assert_eq!(item.string(&strings), Some("Right"));
+        assert_eq!(ExpectedFieldItem::default().string(&strings), None);
+    }
+}
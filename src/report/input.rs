@@ -1,6 +1,10 @@
-use super::InputError;
-use crate::report::expected::{ExpectedField, ExpectedFieldItem};
-use crate::report_descriptor::{GlobalType, Mutability, ReportDescriptorItem, Structure};
+use super::{InputError, ReportType};
+use crate::report::expected::{ExpectedField, ExpectedFieldItem, UsageRange};
+use crate::report_descriptor::data::SizedPayload;
+use crate::report_descriptor::unit::Unit;
+use crate::report_descriptor::{
+    GlobalType, ItemType, MainType, Mutability, ReportDescriptorItem, Structure,
+};
 use crate::usage_table::{Usage, UsagePage};
 
 #[derive(Default, Debug, PartialEq, Clone)]
@@ -10,14 +14,41 @@ pub struct GlobalItemTracker {
     report_id: Option<u8>,
     report_count: Option<u32>,
     logical_minimum: Option<i32>,
-    logical_maximum: Option<i32>,
+    logical_maximum: Option<SizedPayload>,
+    physical_minimum: Option<i32>,
+    physical_maximum: Option<SizedPayload>,
+    unit_exponent: Option<i32>,
+    unit: Option<Unit>,
+    stack: Vec<GlobalItemTracker>,
 }
 
 #[derive(Default, Clone)]
 pub struct LocalItemTracker<'a> {
-    usage: Vec<&'a ReportDescriptorItem>,
-    usage_minimum: Option<u16>,
-    usage_maximum: Option<u16>,
+    usages: Vec<UsageEntry<'a>>, // In order of declaration
+    delimiter_open: bool,
+    set_start: usize, // Index in usages of the open Delimiter set
+    usage_minimum: Option<&'a ReportDescriptorItem>, // Waiting for its Usage Maximum
+    usage_maximum: Option<&'a ReportDescriptorItem>, // Waiting for its Usage Minimum
+    designator: IndexTracker,
+    string: IndexTracker,
+}
+
+/// A Usage item or a Usage Minimum/Maximum range
+#[derive(Clone)]
+enum UsageEntry<'a> {
+    /// Primary usage followed by the alternate usages of its Delimiter set
+    Set(Vec<&'a ReportDescriptorItem>),
+
+    /// Usage Minimum and Usage Maximum
+    Range(&'a ReportDescriptorItem, &'a ReportDescriptorItem),
+}
+
+/// Designator or String indices, listed one by one or as Minimum/Maximum ranges
+#[derive(Default, Clone)]
+struct IndexTracker {
+    ranges: Vec<(u32, u32)>, // In order of declaration, a single index as a range of one
+    minimum: Option<u32>,    // Waiting for its Maximum
+    maximum: Option<u32>,    // Waiting for its Minimum
 }
 
 impl GlobalItemTracker {
@@ -35,7 +66,7 @@ impl GlobalItemTracker {
     }
 
     /// Get the Usage Page as reference
-    fn usage_page(&self) -> Option<&UsagePage> {
+    pub(crate) fn usage_page(&self) -> Option<&UsagePage> {
         self.usage_page.as_ref()
     }
 
@@ -71,8 +102,8 @@ impl GlobalItemTracker {
         Ok(self)
     }
 
-    /// Get the Report ID as a SizedPayload
-    fn report_id(&self) -> Option<u8> {
+    /// Get the Report ID
+    pub(crate) fn report_id(&self) -> Option<u8> {
         self.report_id
     }
 
@@ -116,9 +147,136 @@ impl GlobalItemTracker {
             return Err(InputError::InvalidItemType);
         }
 
-        self.logical_maximum = Some(item.payload_i32());
+        self.logical_maximum = Some(item.raw_payload());
         Ok(self)
     }
+
+    /// Get the Logical Minimum
+    fn logical_minimum(&self) -> Option<i64> {
+        self.logical_minimum.map(i64::from)
+    }
+
+    /// Get the Logical Maximum, interpreted against the Logical Minimum
+    fn logical_maximum(&self) -> Option<i64> {
+        let minimum = self.logical_minimum.unwrap_or_default();
+        self.logical_maximum
+            .as_ref()
+            .map(|maximum| resolve_maximum(minimum, maximum))
+    }
+
+    /// Set the Physical Minimum
+    pub(crate) fn set_physical_minimum(
+        &mut self,
+        item: &ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_physical_minimum() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        self.physical_minimum = Some(item.payload_i32());
+        Ok(self)
+    }
+
+    /// Set the Physical Maximum
+    pub(crate) fn set_physical_maximum(
+        &mut self,
+        item: &ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_physical_maximum() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        self.physical_maximum = Some(item.raw_payload());
+        Ok(self)
+    }
+
+    /// Get the Physical Minimum
+    fn physical_minimum(&self) -> Option<i64> {
+        self.physical_minimum.map(i64::from)
+    }
+
+    /// Get the Physical Maximum, interpreted against the Physical Minimum
+    fn physical_maximum(&self) -> Option<i64> {
+        let minimum = self.physical_minimum.unwrap_or_default();
+        self.physical_maximum
+            .as_ref()
+            .map(|maximum| resolve_maximum(minimum, maximum))
+    }
+
+    /// Set the Unit Exponent
+    pub(crate) fn set_unit_exponent(
+        &mut self,
+        item: &ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        self.unit_exponent = Some(item.unit_exponent().ok_or(InputError::InvalidItemType)?);
+        Ok(self)
+    }
+
+    /// Get the Unit Exponent
+    fn unit_exponent(&self) -> Option<i32> {
+        self.unit_exponent
+    }
+
+    /// Set the Unit
+    pub(crate) fn set_unit(&mut self, item: &ReportDescriptorItem) -> Result<&Self, InputError> {
+        self.unit = Some(item.unit().ok_or(InputError::InvalidItemType)?);
+        Ok(self)
+    }
+
+    /// Get the Unit
+    fn unit(&self) -> Option<&Unit> {
+        self.unit.as_ref()
+    }
+
+    /// Place a copy of the global item state on the stack
+    pub(crate) fn push(&mut self, item: &ReportDescriptorItem) -> Result<&Self, InputError> {
+        if !item.is_push() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        let state = GlobalItemTracker {
+            stack: Vec::new(),
+            ..self.clone()
+        };
+
+        self.stack.push(state);
+        Ok(self)
+    }
+
+    /// Replace the global item state with the top of the stack
+    pub(crate) fn pop(&mut self, item: &ReportDescriptorItem) -> Result<&Self, InputError> {
+        if !item.is_pop() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        let mut state = self.stack.pop().ok_or(InputError::GlobalStackEmpty)?;
+        state.stack = std::mem::take(&mut self.stack);
+        *self = state;
+
+        Ok(self)
+    }
+
+    /// Apply any global item, including Push and Pop
+    ///
+    /// Reserved global items are ignored, like the Linux kernel does.
+    pub(crate) fn apply(&mut self, item: &ReportDescriptorItem) -> Result<&Self, InputError> {
+        match &item.kind {
+            ItemType::Global(GlobalType::UsagePage) => self.set_usage_page(item),
+            ItemType::Global(GlobalType::LogicalMinimum) => self.set_logical_minimum(item),
+            ItemType::Global(GlobalType::LogicalMaximum) => self.set_logical_maximum(item),
+            ItemType::Global(GlobalType::PhysicalMinimum) => self.set_physical_minimum(item),
+            ItemType::Global(GlobalType::PhysicalMaximum) => self.set_physical_maximum(item),
+            ItemType::Global(GlobalType::UnitExponent) => self.set_unit_exponent(item),
+            ItemType::Global(GlobalType::Unit) => self.set_unit(item),
+            ItemType::Global(GlobalType::ReportSize) => self.set_report_size(item),
+            ItemType::Global(GlobalType::ReportID) => self.set_report_id(item),
+            ItemType::Global(GlobalType::ReportCount) => self.set_report_count(item),
+            ItemType::Global(GlobalType::Push) => self.push(item),
+            ItemType::Global(GlobalType::Pop) => self.pop(item),
+            ItemType::Global(GlobalType::Reserved(_)) => Ok(self),
+            _ => Err(InputError::InvalidItemType),
+        }
+    }
 }
 
 impl<'a> LocalItemTracker<'a> {
@@ -130,10 +288,170 @@ impl<'a> LocalItemTracker<'a> {
             return Err(InputError::InvalidItemType);
         }
 
-        self.usage.push(item);
+        match (
+            self.delimiter_open,
+            self.set_has_primary(),
+            self.usages.last_mut(),
+        ) {
+            (true, true, Some(UsageEntry::Set(set))) => set.push(item),
+            (true, true, _) => {} // An alternate of a usage range
+            _ => self.usages.push(UsageEntry::Set(vec![item])),
+        }
+
+        Ok(self)
+    }
+
+    pub(crate) fn add_designator_index(
+        &mut self,
+        item: &'a ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_designator_index() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        let index = item.payload_u32();
+        self.designator.ranges.push((index, index));
+        Ok(self)
+    }
+
+    pub(crate) fn set_designator_minimum(
+        &mut self,
+        item: &'a ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_designator_minimum() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        self.designator.minimum = Some(item.payload_u32());
+        self.designator.add_range();
+        Ok(self)
+    }
+
+    pub(crate) fn set_designator_maximum(
+        &mut self,
+        item: &'a ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_designator_maximum() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        self.designator.maximum = Some(item.payload_u32());
+        self.designator.add_range();
+        Ok(self)
+    }
+
+    pub(crate) fn add_string_index(
+        &mut self,
+        item: &'a ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_string_index() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        let index = item.payload_u32();
+        self.string.ranges.push((index, index));
         Ok(self)
     }
 
+    pub(crate) fn set_string_minimum(
+        &mut self,
+        item: &'a ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_string_minimum() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        self.string.minimum = Some(item.payload_u32());
+        self.string.add_range();
+        Ok(self)
+    }
+
+    pub(crate) fn set_string_maximum(
+        &mut self,
+        item: &'a ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_string_maximum() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        self.string.maximum = Some(item.payload_u32());
+        self.string.add_range();
+        Ok(self)
+    }
+
+    /// Open (payload 1) or close (payload 0) a set of alternate usages. All usages
+    /// in a set describe the same control, the first one being the primary usage.
+    ///
+    /// For definition in the HID protocol see
+    /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 41
+    pub(crate) fn set_delimiter(
+        &mut self,
+        item: &'a ReportDescriptorItem,
+    ) -> Result<&Self, InputError> {
+        if !item.is_delimiter() {
+            return Err(InputError::InvalidItemType);
+        }
+
+        match (item.payload_u32(), self.delimiter_open) {
+            (1, false) => {
+                self.delimiter_open = true;
+                self.set_start = self.usages.len();
+            }
+            (0, true) => self.delimiter_open = false,
+            _ => return Err(InputError::InvalidDelimiter),
+        }
+
+        Ok(self)
+    }
+
+    /// Check that no Delimiter set is open when a main item ends the local items
+    pub(crate) fn close(&self) -> Result<&Self, InputError> {
+        match self.delimiter_open {
+            true => Err(InputError::InvalidDelimiter),
+            false => Ok(self),
+        }
+    }
+
+    /// Returns true if the open Delimiter set already has its primary usage or range,
+    /// later usages in the set are alternates
+    fn set_has_primary(&self) -> bool {
+        self.delimiter_open && self.usages.len() > self.set_start
+    }
+
+    /// Add the range once both Usage Minimum and Usage Maximum are known
+    fn add_usage_range(&mut self) {
+        if let (Some(minimum), Some(maximum)) = (self.usage_minimum, self.usage_maximum) {
+            self.usages.push(UsageEntry::Range(minimum, maximum));
+            self.usage_minimum = None;
+            self.usage_maximum = None;
+        }
+    }
+
+    /// Get the usages in order of declaration, each Usage item as a range of one usage
+    /// together with its alternate usages
+    fn declared_usages(&self, usage_page: &UsagePage) -> Vec<(UsageRange, Vec<Usage>)> {
+        self.usages
+            .iter()
+            .filter_map(|entry| match entry {
+                UsageEntry::Set(set) => {
+                    let (page, id) = set.first()?.usage_page_and_id(usage_page)?;
+                    let alternates = set
+                        .iter()
+                        .skip(1)
+                        .filter_map(|item| item.usage_page_and_id(usage_page))
+                        .map(|(page, id)| Usage::from((&page, id)))
+                        .collect();
+                    Some((UsageRange::from((page, id, id)), alternates))
+                }
+                UsageEntry::Range(minimum, maximum) => {
+                    let (page, minimum) = minimum.usage_page_and_id(usage_page)?;
+                    let (_, maximum) = maximum.usage_page_and_id(usage_page)?;
+                    Some((UsageRange::from((page, minimum, maximum)), Vec::new()))
+                }
+            })
+            .collect()
+    }
+
     pub(crate) fn set_usage_minimum(
         &mut self,
         item: &'a ReportDescriptorItem,
@@ -142,7 +460,15 @@ impl<'a> LocalItemTracker<'a> {
             return Err(InputError::InvalidItemType);
         }
 
-        self.usage_minimum = Some(item.payload_u16().ok_or(InputError::InvalidPayload)?);
+        if !item.is_extended_usage() && item.payload_u16().is_none() {
+            return Err(InputError::InvalidPayload);
+        }
+
+        if !self.set_has_primary() {
+            self.usage_minimum = Some(item);
+            self.add_usage_range();
+        }
+
         Ok(self)
     }
 
@@ -154,19 +480,41 @@ impl<'a> LocalItemTracker<'a> {
             return Err(InputError::InvalidItemType);
         }
 
-        self.usage_maximum = Some(item.payload_u16().ok_or(InputError::InvalidPayload)?);
+        if !item.is_extended_usage() && item.payload_u16().is_none() {
+            return Err(InputError::InvalidPayload);
+        }
+
+        if !self.set_has_primary() {
+            self.usage_maximum = Some(item);
+            self.add_usage_range();
+        }
+
         Ok(self)
     }
 }
 
+/// Resolve a maximum against its minimum
+///
+/// Many descriptors encode a maximum like 255 in a single byte (`0x25 0xff`), which
+/// reads as -1 when taken as a signed value. Like the Linux kernel, the maximum is only
+/// read as a signed value if the minimum is negative.
+pub(crate) fn resolve_maximum(minimum: i32, maximum: &SizedPayload) -> i64 {
+    if minimum < 0 {
+        i64::from(i32::from(maximum.clone()))
+    } else {
+        i64::from(u32::from(maximum.clone()))
+    }
+}
+
+/// An Input, Output or Feature item together with the item state it was declared in
 #[derive(Clone)]
-pub struct Input<'a> {
-    input_item: &'a ReportDescriptorItem,
+pub struct DataItem<'a> {
+    item: &'a ReportDescriptorItem,
     global_items: GlobalItemTracker,
     local_items: LocalItemTracker<'a>,
 }
 
-impl<'a> Input<'a> {
+impl<'a> DataItem<'a> {
     pub fn report_id(&self) -> Option<u8> {
         self.global_items.report_id()
     }
@@ -174,80 +522,86 @@ impl<'a> Input<'a> {
 
 impl<'a>
     TryFrom<(
+        ReportType,
         &'a ReportDescriptorItem,
         GlobalItemTracker,
         LocalItemTracker<'a>,
-    )> for Input<'a>
+    )> for DataItem<'a>
 {
     type Error = InputError;
 
     fn try_from(
         value: (
+            ReportType,
             &'a ReportDescriptorItem,
             GlobalItemTracker,
             LocalItemTracker<'a>,
         ),
     ) -> Result<Self, Self::Error> {
-        if !value.0.is_input() {
+        if value.1.kind != ItemType::Main(MainType::from(value.0)) {
             return Err(InputError::InvalidItemType);
         }
 
-        Ok(Input {
-            input_item: value.0,
-            global_items: value.1,
-            local_items: value.2,
+        Ok(DataItem {
+            item: value.1,
+            global_items: value.2,
+            local_items: value.3,
         })
     }
 }
 
 pub fn expected_fields(
-    input: Input,
+    data_item: DataItem,
     index_in_raw: usize,
 ) -> Result<Vec<ExpectedField>, InputError> {
     let default_usage_page = UsagePage::default();
-    let usage_page = input
+    let usage_page = data_item
         .global_items
         .usage_page()
         .unwrap_or(&default_usage_page);
 
-    let options = input
-        .input_item
+    let options = data_item
+        .item
         .data_field_options()
         .ok_or(InputError::InvalidPayload)?;
 
-    let report_size = input
+    let report_size = data_item
         .global_items
         .report_size()
         .ok_or(InputError::GlobalItemNotSet(GlobalType::ReportSize))?;
 
-    let report_count = input
+    let report_count = data_item
         .global_items
         .report_count()
         .ok_or(InputError::GlobalItemNotSet(GlobalType::ReportCount))?;
 
+    let declared_usages = data_item.local_items.declared_usages(usage_page);
+    let array_usages: Vec<UsageRange> = match (options.mutability(), options.structure()) {
+        (Mutability::Data, Structure::Array) => declared_usages
+            .iter()
+            .map(|(range, _)| range.clone())
+            .collect(),
+        _ => Vec::new(),
+    };
+
     let mut expected_fields: Vec<ExpectedFieldItem> = Vec::new();
     // Create a bunch of ExpectedFieldItems
     for i in 0..report_count as usize {
-        let usage_page = usage_page.clone();
         let options = options.clone();
 
-        let usage = match (
-            options.mutability(),
-            options.structure(),
-            input.local_items.usage_minimum,
-            input.local_items.usage_maximum,
-            input.local_items.usage.get(i),
-        ) {
-            (Mutability::Data, Structure::Variable, Some(min), Some(_max), _) => {
-                Usage::from((&usage_page, min + i as u16))
-            }
-            (Mutability::Data, Structure::Variable, _, _, Some(&item)) => {
-                item.usage(&usage_page).unwrap_or_default()
+        // Array items select their usage by value, see array_usages
+        let control = match (options.mutability(), options.structure()) {
+            (Mutability::Data, Structure::Array) => None,
+            _ => control_usage(&declared_usages, i),
+        };
+        let (usage_page, usage, alternate_usages) = match (control, options.structure()) {
+            (Some((page, id, alternates)), Structure::Variable) => {
+                (page.clone(), Usage::from((&page, id)), alternates.to_vec())
             }
-            (Mutability::Constant, _, _, _, Some(&item)) => {
-                item.usage(&usage_page).unwrap_or_default()
+            (Some((page, id, _)), Structure::Array) => {
+                (page.clone(), Usage::from((&page, id)), Vec::new())
             }
-            _ => Usage::default(),
+            (None, _) => (usage_page.clone(), Usage::default(), Vec::new()),
         };
 
         let size_bits = report_size as usize;
@@ -259,6 +613,16 @@ pub fn expected_fields(
             index_in_raw,
             size_bits,
             options,
+            logical_minimum: data_item.global_items.logical_minimum(),
+            logical_maximum: data_item.global_items.logical_maximum(),
+            physical_minimum: data_item.global_items.physical_minimum(),
+            physical_maximum: data_item.global_items.physical_maximum(),
+            unit_exponent: data_item.global_items.unit_exponent(),
+            unit: data_item.global_items.unit().cloned(),
+            array_usages: array_usages.clone(),
+            alternate_usages,
+            designator_index: data_item.local_items.designator.get(i),
+            string_index: data_item.local_items.string.get(i),
         };
 
         expected_fields.push(item);
@@ -282,11 +646,77 @@ pub fn expected_fields(
     Ok(expected_fields)
 }
 
+impl IndexTracker {
+    /// Add the range once both Minimum and Maximum are known
+    fn add_range(&mut self) {
+        if let (Some(minimum), Some(maximum)) = (self.minimum, self.maximum) {
+            self.ranges.push((minimum, maximum));
+            self.minimum = None;
+            self.maximum = None;
+        }
+    }
+
+    /// Get the index of the n-th control
+    ///
+    /// Ranges are expanded, one index per control. Controls past the declared indices
+    /// get the last one, the same rule as for usages (see [`control_usage`]).
+    fn get(&self, n: usize) -> Option<u32> {
+        let len = |(minimum, maximum): &(u32, u32)| {
+            (u64::from(*maximum) + 1).saturating_sub(u64::from(*minimum))
+        };
+
+        let mut n = u64::try_from(n).ok()?;
+        for range in &self.ranges {
+            if n < len(range) {
+                return range.0.checked_add(u32::try_from(n).ok()?);
+            }
+            n -= len(range);
+        }
+
+        self.ranges
+            .iter()
+            .rev()
+            .find(|range| len(range) > 0)
+            .map(|(_, maximum)| *maximum)
+    }
+}
+
+/// Get the usage of the n-th control of a variable item with its alternate usages
+///
+/// Usage ranges are expanded, one usage per control. Controls past the declared usages
+/// get the last usage, like the Linux HID parser does.
+fn control_usage(
+    usages: &[(UsageRange, Vec<Usage>)],
+    n: usize,
+) -> Option<(UsagePage, u16, &[Usage])> {
+    let mut n = u64::try_from(n).ok()?;
+    for (range, alternates) in usages {
+        if n < range.len() {
+            let id = range.minimum.checked_add(u16::try_from(n).ok()?)?;
+            return Some((range.usage_page.clone(), id, alternates));
+        }
+        n -= range.len();
+    }
+
+    let (range, alternates) = usages.iter().rev().find(|(range, _)| range.len() > 0)?;
+    Some((range.usage_page.clone(), range.maximum, alternates))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::report_descriptor::data::Size;
-    use crate::report_descriptor::{ItemType, MainType};
+
+    #[test]
+    fn maximum_is_unsigned_for_positive_minimum() {
+        assert_eq!(resolve_maximum(0, &SizedPayload::from([0xff])), 255);
+    }
+
+    #[test]
+    fn maximum_is_signed_for_negative_minimum() {
+        assert_eq!(resolve_maximum(-127, &SizedPayload::from([0x7f])), 127);
+        assert_eq!(resolve_maximum(-127, &SizedPayload::from([0xff])), -1);
+    }
 
     #[test]
     fn input_struct_from_input_descriptor_item_is_ok() {
@@ -296,7 +726,8 @@ mod tests {
             raw: vec![],
         };
 
-        let result = Input::try_from((
+        let result = DataItem::try_from((
+            ReportType::Input,
             &input_item,
             GlobalItemTracker::default(),
             LocalItemTracker::default(),
@@ -304,6 +735,23 @@ mod tests {
         assert!(result.is_ok())
     }
 
+    #[test]
+    fn output_struct_from_output_descriptor_item_is_ok() {
+        let output_item = ReportDescriptorItem {
+            kind: ItemType::Main(MainType::Output),
+            payload_size: Size::Empty,
+            raw: vec![],
+        };
+
+        let result = DataItem::try_from((
+            ReportType::Output,
+            &output_item,
+            GlobalItemTracker::default(),
+            LocalItemTracker::default(),
+        ));
+        assert!(result.is_ok())
+    }
+
     #[test]
     fn input_struct_from_non_input_descriptor_item_is_err() {
         let input_item = ReportDescriptorItem {
@@ -312,7 +760,8 @@ mod tests {
             raw: vec![],
         };
 
-        let result = Input::try_from((
+        let result = DataItem::try_from((
+            ReportType::Input,
             &input_item,
             GlobalItemTracker::default(),
             LocalItemTracker::default(),
@@ -1,16 +1,23 @@
-mod expected;
-mod input;
+/// Semantic differences between Report Descriptors
+pub mod diff;
+/// Expected reports derived from a Report Descriptor
+pub mod expected;
+pub(crate) mod input;
 mod parse;
 /// Parsed Report data
 pub mod parsed;
+/// Report lengths checked against the limits of a transport
+pub mod transport;
 
 use crate::report::expected::{ExpectedReport, ExpectedReports};
-use crate::report::input::{GlobalItemTracker, Input, LocalItemTracker};
-use crate::report::parse::{report_id, val};
+use crate::report::input::{DataItem, GlobalItemTracker, LocalItemTracker};
+use crate::report::parse::{report_id, sign_extend, val};
 use crate::report::parsed::{Field, ParsedReport};
 use crate::report_descriptor::{GlobalType, ItemType, LocalType, MainType, ReportDescriptor};
 use thiserror::Error;
 
+pub use crate::report::parse::ParseError;
+
 /// Errors for working with an Report
 #[derive(Error, Debug, PartialEq)]
 pub enum InputError {
@@ -46,13 +53,65 @@ pub enum InputError {
     #[error("An array item was expected")]
     ArrayItemExpected,
 
+    /// A Delimiter set was opened twice, closed without being opened or is still open
+    /// at the next main item
+    #[error("Unbalanced or nested Delimiter")]
+    InvalidDelimiter,
+
+    /// An array value that does not select any of the declared usages
+    #[error("Array value does not select a usage")]
+    ArrayValueWithoutUsage,
+
     /// An variable item was expected
     #[error("An variable item was expected")]
     VariableItemExpected,
 
     /// Can not take bits from input
-    #[error("Can not take bits from input")]
-    CannotTakeBits,
+    #[error("Can not take bits from input: {0}")]
+    CannotTakeBits(#[from] ParseError),
+
+    /// A Pop item was found while the global item stack is empty
+    #[error("Pop without a matching Push")]
+    GlobalStackEmpty,
+}
+
+/// The kind of report a data field belongs to
+///
+/// Every Input, Output and Feature main item adds data fields to a report of the
+/// matching type. A device can use the same Report ID for reports of different types.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ReportType {
+    /// Report sent from the device to the host
+    Input,
+
+    /// Report sent from the host to the device
+    Output,
+
+    /// Configuration report, read and written with GET_REPORT/SET_REPORT
+    Feature,
+}
+
+impl TryFrom<&MainType> for ReportType {
+    type Error = ();
+
+    fn try_from(value: &MainType) -> Result<Self, Self::Error> {
+        match value {
+            MainType::Input => Ok(ReportType::Input),
+            MainType::Output => Ok(ReportType::Output),
+            MainType::Feature => Ok(ReportType::Feature),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<ReportType> for MainType {
+    fn from(value: ReportType) -> Self {
+        match value {
+            ReportType::Input => MainType::Input,
+            ReportType::Output => MainType::Output,
+            ReportType::Feature => MainType::Feature,
+        }
+    }
 }
 
 /// Create a list of expected reports from the Report Descriptor
@@ -77,43 +136,139 @@ pub enum InputError {
 pub fn expected_input_reports(
     report_descriptor: &ReportDescriptor,
 ) -> Result<ExpectedReports, InputError> {
+    expected_reports(report_descriptor, ReportType::Input)
+}
+
+/// Create a list of expected Output reports from the Report Descriptor
+///
+/// Output reports are sent from the host to the device, for example to turn on
+/// the LEDs of a keyboard.
+///
+/// # Example
+/// ```
+/// use hid_tools::report::expected_output_reports;
+/// use hid_tools::report_builder::ReportDescriptorBuilder;
+/// use hid_tools::usage_table::UsagePage;
+///
+/// let report_descriptor = ReportDescriptorBuilder::new()
+///     .usage_page(UsagePage::LED)
+///     .usage_minimum::<u16>(1)
+///     .usage_maximum::<u16>(5)
+///     .report_size(1)
+///     .report_count(5)
+///     .output(0x02) // Data, Variable, Absolute
+///     .build();
+///
+/// let expected = expected_output_reports(&report_descriptor).unwrap();
+/// assert_eq!(expected.reports()[0].size(), 5);
+/// ```
+pub fn expected_output_reports(
+    report_descriptor: &ReportDescriptor,
+) -> Result<ExpectedReports, InputError> {
+    expected_reports(report_descriptor, ReportType::Output)
+}
+
+/// Create a list of expected Feature reports from the Report Descriptor
+///
+/// Feature reports carry device configuration and are read or written by the host
+/// with GET_REPORT and SET_REPORT requests.
+///
+/// # Example
+/// ```
+/// use hid_tools::report::expected_feature_reports;
+/// use hid_tools::report_builder::ReportDescriptorBuilder;
+/// use hid_tools::usage_table::UsagePage;
+///
+/// let report_descriptor = ReportDescriptorBuilder::new()
+///     .usage_page(UsagePage::VendorDefined(0xff00))
+///     .report_id(5)
+///     .usage::<u16>(0x01)
+///     .report_size(8)
+///     .report_count(2)
+///     .feature(0x02) // Data, Variable, Absolute
+///     .build();
+///
+/// let expected = expected_feature_reports(&report_descriptor).unwrap();
+/// assert_eq!(expected.reports()[0].size(), 24);
+/// ```
+pub fn expected_feature_reports(
+    report_descriptor: &ReportDescriptor,
+) -> Result<ExpectedReports, InputError> {
+    expected_reports(report_descriptor, ReportType::Feature)
+}
+
+/// Create a list of expected reports of one report type from the Report Descriptor
+fn expected_reports(
+    report_descriptor: &ReportDescriptor,
+    report_type: ReportType,
+) -> Result<ExpectedReports, InputError> {
+    let (has_report_id, report_items) = report_items(report_descriptor)?;
+
+    let reports = report_items
+        .into_iter()
+        .filter(|r| r.report_type == report_type)
+        .map(|r| ExpectedReport::try_from(r.data_items))
+        .collect::<Result<Vec<ExpectedReport>, InputError>>()?;
+
+    Ok(ExpectedReports::from((has_report_id, reports)))
+}
+
+/// All data items that make up one report
+struct ReportItems<'a> {
+    report_type: ReportType,
+    report_id: Option<u8>,
+    data_items: Vec<DataItem<'a>>,
+}
+
+/// Walk the Report Descriptor and group all data items by report type and Report ID
+///
+/// Fields of one report can be spread over several (nested or sibling) collections, so
+/// the items are accumulated over the whole descriptor. Reports are kept in order of
+/// their first appearance. Also returns whether a Report ID was declared at all.
+fn report_items(
+    report_descriptor: &ReportDescriptor,
+) -> Result<(bool, Vec<ReportItems<'_>>), InputError> {
     let mut global_items = GlobalItemTracker::default();
     let mut local_items = LocalItemTracker::default();
-    let mut inputs: Vec<Input> = Vec::new();
     let mut has_report_id = false;
-    let mut reports: Vec<ExpectedReport> = Vec::new();
+    let mut reports: Vec<ReportItems> = Vec::new();
 
     for item in report_descriptor.items() {
-        match item.kind {
-            ItemType::Main(MainType::Input) => {
-                let input = Input::try_from((item, global_items.clone(), local_items.clone()))?;
-                inputs.push(input);
-                local_items = LocalItemTracker::default();
-            }
-            ItemType::Main(MainType::EndCollection) => {
-                if !inputs.is_empty() {
-                    reports.push(ExpectedReport::try_from(inputs.to_vec())?);
-                    inputs = Vec::new();
+        match &item.kind {
+            ItemType::Main(main_type) => {
+                local_items.close()?;
+
+                if let Ok(report_type) = ReportType::try_from(main_type) {
+                    let data_item = DataItem::try_from((
+                        report_type,
+                        item,
+                        global_items.clone(),
+                        local_items.clone(),
+                    ))?;
+
+                    let report_id = data_item.report_id();
+                    match reports
+                        .iter_mut()
+                        .find(|r| r.report_type == report_type && r.report_id == report_id)
+                    {
+                        Some(report) => report.data_items.push(data_item),
+                        None => reports.push(ReportItems {
+                            report_type,
+                            report_id,
+                            data_items: vec![data_item],
+                        }),
+                    }
                 }
-            }
-            ItemType::Global(GlobalType::UsagePage) => {
-                global_items.set_usage_page(item)?;
-            }
-            ItemType::Global(GlobalType::ReportSize) => {
-                global_items.set_report_size(item)?;
+
+                // Local items only apply to the next main item, whatever its type
+                local_items = LocalItemTracker::default();
             }
             ItemType::Global(GlobalType::ReportID) => {
                 has_report_id = true;
-                global_items.set_report_id(item)?;
+                global_items.apply(item)?;
             }
-            ItemType::Global(GlobalType::ReportCount) => {
-                global_items.set_report_count(item)?;
-            }
-            ItemType::Global(GlobalType::LogicalMinimum) => {
-                global_items.set_logical_minimum(item)?;
-            }
-            ItemType::Global(GlobalType::LogicalMaximum) => {
-                global_items.set_logical_maximum(item)?;
+            ItemType::Global(_) => {
+                global_items.apply(item)?;
             }
             ItemType::Local(LocalType::Usage) => {
                 local_items.add_usage(item)?;
@@ -124,15 +279,33 @@ pub fn expected_input_reports(
             ItemType::Local(LocalType::UsageMaximum) => {
                 local_items.set_usage_maximum(item)?;
             }
-            _ => {}
+            ItemType::Local(LocalType::DesignatorIndex) => {
+                local_items.add_designator_index(item)?;
+            }
+            ItemType::Local(LocalType::DesignatorMinimum) => {
+                local_items.set_designator_minimum(item)?;
+            }
+            ItemType::Local(LocalType::DesignatorMaximum) => {
+                local_items.set_designator_maximum(item)?;
+            }
+            ItemType::Local(LocalType::StringIndex) => {
+                local_items.add_string_index(item)?;
+            }
+            ItemType::Local(LocalType::StringMinimum) => {
+                local_items.set_string_minimum(item)?;
+            }
+            ItemType::Local(LocalType::StringMaximum) => {
+                local_items.set_string_maximum(item)?;
+            }
+            ItemType::Local(LocalType::Delimiter) => {
+                local_items.set_delimiter(item)?;
+            }
+            ItemType::Long(_) => {} // vendor specific, no meaning for the report layout
+            ItemType::Local(LocalType::Reserved(_)) | ItemType::Reserved(_) => {} // ignored, like the Linux kernel does
         }
     }
 
-    if !inputs.is_empty() {
-        reports.push(ExpectedReport::try_from(inputs)?);
-    }
-
-    Ok(ExpectedReports::from((has_report_id, reports)))
+    Ok((has_report_id, reports))
 }
 
 /// Parse raw input report
@@ -158,6 +331,43 @@ pub fn expected_input_reports(
 pub fn parse_raw_input_report(
     report: &[u8],
     expected_reports: &ExpectedReports,
+) -> Result<ParsedReport, InputError> {
+    parse_raw_report(report, expected_reports)
+}
+
+/// Parse raw feature report, as returned by a GET_REPORT (Feature) request
+///
+/// # Example
+/// ```
+/// use hid_tools::report::{expected_feature_reports, parse_raw_feature_report};
+/// use hid_tools::report_builder::ReportDescriptorBuilder;
+/// use hid_tools::usage_table::UsagePage;
+///
+/// let report_descriptor = ReportDescriptorBuilder::new()
+///     .usage_page(UsagePage::VendorDefined(0xff00))
+///     .report_id(5)
+///     .usage::<u16>(0x01)
+///     .report_size(8)
+///     .report_count(1)
+///     .feature(0x02) // Data, Variable, Absolute
+///     .build();
+///
+/// let expected_reports = expected_feature_reports(&report_descriptor).unwrap();
+/// let parsed_report = parse_raw_feature_report(&[5, 42], &expected_reports).unwrap();
+///
+/// println!("{}", parsed_report);
+/// ```
+pub fn parse_raw_feature_report(
+    report: &[u8],
+    expected_reports: &ExpectedReports,
+) -> Result<ParsedReport, InputError> {
+    parse_raw_report(report, expected_reports)
+}
+
+/// Parse a raw report of any report type
+fn parse_raw_report(
+    report: &[u8],
+    expected_reports: &ExpectedReports,
 ) -> Result<ParsedReport, InputError> {
     // get Report ID if expected
     let report_id = match expected_reports.has_report_id {
@@ -179,8 +389,12 @@ pub fn parse_raw_input_report(
             report,
             expected_field.index() as u32,
             expected_field.size() as u32,
-        )
-        .map_err(|_| InputError::CannotTakeBits)?;
+        )?;
+
+        let data = match expected_field.is_signed() {
+            true => sign_extend(data, expected_field.size() as u32),
+            false => data,
+        };
 
         parsed_fields.push(Field::try_from((expected_field, data))?);
     }
@@ -194,10 +408,12 @@ pub fn parse_raw_input_report(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::report::expected::{ExpectedField, ExpectedFieldItem};
+    use crate::report::expected::{ExpectedField, ExpectedFieldItem, UsageRange};
     use crate::report::parsed::{Field, VarItem};
     use crate::report_builder::ReportDescriptorBuilder;
+    use crate::report_descriptor::data::SizedPayload;
     use crate::report_descriptor::{Collection, DataFieldOptions, Mutability, Structure, Value};
+    use crate::usage_table::consumer::ConsumerUsage;
     use crate::usage_table::generic_desktop::GenericDesktopControlsUsage;
     use crate::usage_table::keyboard::KeyboardUsage;
     use crate::usage_table::{Usage, UsagePage};
@@ -231,7 +447,8 @@ mod tests {
                             Mutability::Data,
                             Structure::Variable,
                             Value::Relative
-                        ))
+                        )),
+                        ..Default::default()
                     })]
                 }]
             })
@@ -266,7 +483,8 @@ mod tests {
                             Mutability::Constant,
                             Structure::Array,
                             Value::Absolute
-                        ))
+                        )),
+                        ..Default::default()
                     })]
                 }]
             })
@@ -303,7 +521,9 @@ mod tests {
                                 Mutability::Data,
                                 Structure::Array,
                                 Value::Absolute
-                            ))
+                            )),
+                            array_usages: vec![UsageRange::from((UsagePage::Keyboard, 0x00, 0x91))],
+                            ..Default::default()
                         }),
                         ExpectedField::ArrayItem(ExpectedFieldItem {
                             usage_page: UsagePage::Keyboard,
@@ -314,7 +534,9 @@ mod tests {
                                 Mutability::Data,
                                 Structure::Array,
                                 Value::Absolute
-                            ))
+                            )),
+                            array_usages: vec![UsageRange::from((UsagePage::Keyboard, 0x00, 0x91))],
+                            ..Default::default()
                         })
                     ]
                 }]
@@ -352,6 +574,7 @@ mod tests {
                 Structure::Variable,
                 Value::Absolute,
             )),
+            ..Default::default()
         });
 
         let expected_last_field = ExpectedField::Variable(ExpectedFieldItem {
@@ -364,6 +587,7 @@ mod tests {
                 Structure::Variable,
                 Value::Absolute,
             )),
+            ..Default::default()
         });
 
         assert_eq!(result.reports[0].size, 8);
@@ -404,6 +628,7 @@ mod tests {
                 Structure::Variable,
                 Value::Absolute,
             )),
+            ..Default::default()
         });
 
         let expected_last_field = ExpectedField::Constant(ExpectedFieldItem {
@@ -416,6 +641,7 @@ mod tests {
                 Structure::Array,
                 Value::Absolute,
             )),
+            ..Default::default()
         });
 
         assert_eq!(result.reports[0].size, 8);
@@ -423,6 +649,686 @@ mod tests {
         assert_eq!(result.reports[0].fields[5], expected_last_field);
     }
 
+    #[test]
+    fn keyboard_led_output() {
+        // Part from a keyboard report descriptor
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::Keyboard)
+            .collection(Collection::Application)
+            .usage_page(UsagePage::Keyboard)
+            .usage_minimum(KeyboardUsage::KeyboardLeftControl)
+            .usage_maximum(KeyboardUsage::KeyboardRightGUI)
+            .report_count(8)
+            .report_size(1)
+            .input(0x02) // Data, Var, Abs
+            .usage_page(UsagePage::LED)
+            .usage_minimum::<u16>(1)
+            .usage_maximum::<u16>(5)
+            .report_count(5)
+            .output(0x02) // Data, Var, Abs
+            .report_count(1)
+            .report_size(3)
+            .output(0x01) // Constant
+            .end_collection()
+            .build();
+
+        let result = expected_output_reports(&report).unwrap();
+
+        let expected_first_field = ExpectedField::Variable(ExpectedFieldItem {
+            usage_page: UsagePage::LED,
+            usage: Usage::LED(1),
+            index_in_raw: 0,
+            size_bits: 1,
+            options: DataFieldOptions::from((
+                Mutability::Data,
+                Structure::Variable,
+                Value::Absolute,
+            )),
+            ..Default::default()
+        });
+
+        assert_eq!(result.reports.len(), 1);
+        assert_eq!(result.reports[0].size, 8);
+        assert_eq!(result.reports[0].fields.len(), 6);
+        assert_eq!(result.reports[0].fields[0], expected_first_field);
+    }
+
+    #[test]
+    fn output_usages_do_not_leak_into_input() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::Button)
+            .report_size(1)
+            .report_count(1)
+            .usage::<u16>(1)
+            .output(0x02) // Data, Var, Abs
+            .input(0x02) // Data, Var, Abs
+            .build();
+
+        let result = expected_input_reports(&report).unwrap();
+
+        assert_eq!(
+            result.reports[0].fields,
+            vec![ExpectedField::Variable(ExpectedFieldItem {
+                usage_page: UsagePage::Button,
+                usage: Usage::Undefined,
+                index_in_raw: 0,
+                size_bits: 1,
+                options: DataFieldOptions::from((
+                    Mutability::Data,
+                    Structure::Variable,
+                    Value::Absolute,
+                )),
+                ..Default::default()
+            })]
+        );
+    }
+
+    #[test]
+    fn feature_reports_with_report_id() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::VendorDefined(0xff00))
+            .usage::<u16>(0x01)
+            .collection(Collection::Application)
+            .report_id(1)
+            .usage::<u16>(0x02)
+            .report_size(8)
+            .report_count(1)
+            .input(0x02) // Data, Var, Abs
+            .report_id(2)
+            .usage::<u16>(0x03)
+            .usage::<u16>(0x04)
+            .report_size(16)
+            .report_count(2)
+            .feature(0x02) // Data, Var, Abs
+            .end_collection()
+            .build();
+
+        let expected = expected_feature_reports(&report).unwrap();
+        assert!(expected.has_report_id);
+        assert_eq!(expected.reports.len(), 1);
+        assert_eq!(expected.reports[0].report_id, Some(2));
+        assert_eq!(expected.reports[0].size, 40);
+
+        let result = parse_raw_feature_report(&[2, 0x34, 0x12, 0x01, 0x00], &expected).unwrap();
+        assert_eq!(
+            result,
+            ParsedReport {
+                report_id: Some(2),
+                fields: vec![
+                    Field::ReportId(2),
+                    Field::Variable(VarItem {
+                        usage_page: UsagePage::VendorDefined(0xff00),
+                        usage: Usage::VendorDefined(0x03),
+                        value: 0x1234,
+                        options: DataFieldOptions::from((
+                            Mutability::Data,
+                            Structure::Variable,
+                            Value::Absolute
+                        )),
+                        out_of_range: false,
+                        physical_value: 4660.0,
+                        unit: None,
+                        alternate_usages: vec![],
+                    }),
+                    Field::Variable(VarItem {
+                        usage_page: UsagePage::VendorDefined(0xff00),
+                        usage: Usage::VendorDefined(0x04),
+                        value: 1,
+                        options: DataFieldOptions::from((
+                            Mutability::Data,
+                            Structure::Variable,
+                            Value::Absolute
+                        )),
+                        out_of_range: false,
+                        physical_value: 1.0,
+                        unit: None,
+                        alternate_usages: vec![],
+                    })
+                ]
+            }
+        );
+
+        assert_eq!(
+            parse_raw_feature_report(&[1, 0], &expected),
+            Err(InputError::UnknownReportId)
+        );
+    }
+
+    #[test]
+    fn report_id_spread_over_sibling_collections() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::Mouse)
+            .collection(Collection::Application)
+            .report_id(1)
+            .report_size(8)
+            .report_count(1)
+            .collection(Collection::Logical)
+            .usage(GenericDesktopControlsUsage::X)
+            .input(0x06) // Data, Var, Rel
+            .end_collection()
+            .report_id(2)
+            .collection(Collection::Logical)
+            .usage(GenericDesktopControlsUsage::Wheel)
+            .input(0x06) // Data, Var, Rel
+            .end_collection()
+            .report_id(1)
+            .collection(Collection::Logical)
+            .usage(GenericDesktopControlsUsage::Y)
+            .input(0x06) // Data, Var, Rel
+            .end_collection()
+            .end_collection()
+            .build();
+
+        let result = expected_input_reports(&report).unwrap();
+        assert_eq!(result.reports.len(), 2);
+
+        let first = result.find_report(Some(1)).unwrap();
+        assert_eq!(first.size, 24);
+        assert_eq!(
+            first.fields[2],
+            ExpectedField::Variable(ExpectedFieldItem {
+                usage_page: UsagePage::GenericDesktopControls,
+                usage: Usage::GenericDesktopControls(GenericDesktopControlsUsage::Y),
+                index_in_raw: 16,
+                size_bits: 8,
+                options: DataFieldOptions::from((
+                    Mutability::Data,
+                    Structure::Variable,
+                    Value::Relative
+                )),
+                ..Default::default()
+            })
+        );
+
+        let second = result.find_report(Some(2)).unwrap();
+        assert_eq!(second.size, 16);
+    }
+
+    #[test]
+    fn push_and_pop_global_state() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .report_size(8)
+            .report_count(1)
+            .push(SizedPayload::Empty)
+            .usage_page(UsagePage::Button)
+            .report_size(1)
+            .report_count(3)
+            .usage_minimum::<u16>(1)
+            .usage_maximum::<u16>(3)
+            .input(0x02) // Data, Var, Abs
+            .pop(SizedPayload::Empty)
+            .usage(GenericDesktopControlsUsage::Wheel)
+            .input(0x06) // Data, Var, Rel
+            .build();
+
+        let result = expected_input_reports(&report).unwrap();
+
+        assert_eq!(result.reports[0].size, 11);
+        assert_eq!(
+            result.reports[0].fields[3],
+            ExpectedField::Variable(ExpectedFieldItem {
+                usage_page: UsagePage::GenericDesktopControls,
+                usage: Usage::GenericDesktopControls(GenericDesktopControlsUsage::Wheel),
+                index_in_raw: 3,
+                size_bits: 8,
+                options: DataFieldOptions::from((
+                    Mutability::Data,
+                    Structure::Variable,
+                    Value::Relative
+                )),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn pop_on_empty_stack_is_err() {
+        let report = ReportDescriptorBuilder::new()
+            .report_size(8)
+            .pop(SizedPayload::Empty)
+            .build();
+
+        assert_eq!(
+            expected_input_reports(&report),
+            Err(InputError::GlobalStackEmpty)
+        );
+    }
+
+    #[test]
+    fn physical_value_of_digitizer_axis() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::X)
+            .logical_minimum(0)
+            .logical_maximum(4095)
+            .physical_minimum(0)
+            .physical_maximum(2000)
+            .unit(0x11u8) // SI Linear, Length: cm
+            .unit_exponent(0x0eu8) // -2
+            .report_size(16)
+            .report_count(1)
+            .input(0x02) // Data, Var, Abs
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let result = parse_raw_input_report(&[0xff, 0x0f], &expected).unwrap();
+
+        match &result.fields[0] {
+            Field::Variable(item) => {
+                assert_eq!(item.value(), 4095);
+                assert!((item.physical_value() - 20.0).abs() < 1e-9);
+                assert_eq!(item.unit().unwrap().length(), 1);
+            }
+            field => panic!("Expected a variable field, got {:?}", field),
+        }
+    }
+
+    #[test]
+    fn extended_usage_overrides_usage_page() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::Wheel)
+            .extended_usage(UsagePage::Consumer, ConsumerUsage::ACPan)
+            .logical_minimum(-127)
+            .logical_maximum(127)
+            .report_size(8)
+            .report_count(2)
+            .input(0x06) // Data, Var, Rel
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let result = parse_raw_input_report(&[0x01, 0xff], &expected).unwrap();
+
+        match (&result.fields[0], &result.fields[1]) {
+            (Field::Variable(wheel), Field::Variable(pan)) => {
+                assert_eq!(wheel.usage_page(), &UsagePage::GenericDesktopControls);
+                assert_eq!(wheel.value(), 1);
+                assert_eq!(pan.usage_page(), &UsagePage::Consumer);
+                assert_eq!(pan.usage(), &Usage::Consumer(ConsumerUsage::ACPan));
+                assert_eq!(pan.value(), -1);
+            }
+            fields => panic!("Expected two variable fields, got {:?}", fields),
+        }
+    }
+
+    #[test]
+    fn extended_usage_range() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .extended_usage_minimum(UsagePage::Button, 1u16)
+            .extended_usage_maximum(UsagePage::Button, 2u16)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .report_size(1)
+            .report_count(2)
+            .input(0x02)
+            .report_count(6)
+            .input(0x01)
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let fields = expected.reports()[0].fields();
+
+        match &fields[1] {
+            ExpectedField::Variable(item) => {
+                assert_eq!(item.usage_page(), &UsagePage::Button);
+                assert_eq!(item.usage(), &Usage::Button(2));
+            }
+            field => panic!("Expected a variable field, got {:?}", field),
+        }
+    }
+
+    #[test]
+    fn consumer_array_through_logical_minimum() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::Consumer)
+            .usage(ConsumerUsage::PlayPause)
+            .usage_minimum(ConsumerUsage::VolumeIncrement)
+            .usage_maximum(ConsumerUsage::VolumeDecrement)
+            .logical_minimum(1)
+            .logical_maximum(3)
+            .report_size(8)
+            .report_count(3)
+            .input(0x00) // Data, Array, Abs
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let result = parse_raw_input_report(&[0x01, 0x03, 0x07], &expected).unwrap();
+
+        match &result.fields[..] {
+            [Field::ArrayValue(first), Field::ArrayValue(second), Field::ArrayNoUsage(third)] => {
+                assert_eq!(first.usage(), &Usage::Consumer(ConsumerUsage::PlayPause));
+                assert_eq!(
+                    second.usage(),
+                    &Usage::Consumer(ConsumerUsage::VolumeDecrement)
+                );
+                assert_eq!(third.value(), 7);
+            }
+            fields => panic!("Unexpected fields {:?}", fields),
+        }
+
+        let result = parse_raw_input_report(&[0x00, 0x00, 0x00], &expected).unwrap();
+        assert!(result
+            .fields()
+            .iter()
+            .all(|field| matches!(field, Field::ArrayZeroValue(_))));
+
+        assert_eq!(
+            parse_raw_input_report(&[0x01, 0x03], &expected),
+            Err(InputError::CannotTakeBits(ParseError::ReadingBitsFailed(8)))
+        );
+    }
+
+    #[test]
+    fn array_usages_in_order_of_declaration() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::Consumer)
+            .usage_minimum(ConsumerUsage::VolumeIncrement)
+            .usage_maximum(ConsumerUsage::VolumeDecrement)
+            .usage(ConsumerUsage::PlayPause)
+            .logical_minimum(1)
+            .logical_maximum(3)
+            .report_size(8)
+            .report_count(1)
+            .input(0x00) // Data, Array, Abs
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let usages: Vec<Usage> = [0x01, 0x03]
+            .iter()
+            .map(
+                |value| match &parse_raw_input_report(&[*value], &expected).unwrap().fields[..] {
+                    [Field::ArrayValue(item)] => item.usage().clone(),
+                    fields => panic!("Unexpected fields {:?}", fields),
+                },
+            )
+            .collect();
+
+        assert_eq!(
+            usages,
+            vec![
+                Usage::Consumer(ConsumerUsage::VolumeIncrement),
+                Usage::Consumer(ConsumerUsage::PlayPause)
+            ]
+        );
+    }
+
+    #[test]
+    fn array_with_several_usage_ranges() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::Button)
+            .usage_minimum(1u16)
+            .usage_maximum(2u16)
+            .usage_minimum(10u16)
+            .usage_maximum(11u16)
+            .logical_minimum(1)
+            .logical_maximum(4)
+            .report_size(8)
+            .report_count(1)
+            .input(0x00) // Data, Array, Abs
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let usages: Vec<Usage> = [0x01, 0x02, 0x03, 0x04]
+            .iter()
+            .map(
+                |value| match &parse_raw_input_report(&[*value], &expected).unwrap().fields[..] {
+                    [Field::ArrayValue(item)] => item.usage().clone(),
+                    fields => panic!("Unexpected fields {:?}", fields),
+                },
+            )
+            .collect();
+
+        assert_eq!(
+            usages,
+            vec![
+                Usage::Button(1),
+                Usage::Button(2),
+                Usage::Button(10),
+                Usage::Button(11)
+            ]
+        );
+    }
+
+    #[test]
+    fn variable_usage_range_is_clamped_to_maximum() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::Button)
+            .usage_minimum(1u16)
+            .usage_maximum(3u16)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .report_size(1)
+            .report_count(5)
+            .input(0x02)
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let usages: Vec<&Usage> = expected.reports()[0]
+            .fields()
+            .iter()
+            .filter_map(|field| match field {
+                ExpectedField::Variable(item) => Some(item.usage()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            usages,
+            vec![
+                &Usage::Button(1),
+                &Usage::Button(2),
+                &Usage::Button(3),
+                &Usage::Button(3),
+                &Usage::Button(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn delimiter_set_is_one_control() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::X)
+            .delimiter(1)
+            .usage(GenericDesktopControlsUsage::Y)
+            .usage(GenericDesktopControlsUsage::Z)
+            .delimiter(0)
+            .usage(GenericDesktopControlsUsage::Wheel)
+            .logical_minimum(-127)
+            .logical_maximum(127)
+            .report_size(8)
+            .report_count(3)
+            .input(0x06) // Data, Var, Rel
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let result = parse_raw_input_report(&[0x01, 0x02, 0x03], &expected).unwrap();
+
+        match &result.fields[..] {
+            [Field::Variable(x), Field::Variable(y), Field::Variable(wheel)] => {
+                assert!(x.alternate_usages().is_empty());
+                assert_eq!(
+                    y.usage(),
+                    &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Y)
+                );
+                assert_eq!(
+                    y.alternate_usages(),
+                    &vec![Usage::GenericDesktopControls(
+                        GenericDesktopControlsUsage::Z
+                    )]
+                );
+                assert_eq!(
+                    wheel.usage(),
+                    &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Wheel)
+                );
+            }
+            fields => panic!("Unexpected fields {:?}", fields),
+        }
+    }
+
+    #[test]
+    fn unbalanced_delimiter_is_err() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .delimiter(0)
+            .usage(GenericDesktopControlsUsage::X)
+            .report_size(8)
+            .report_count(1)
+            .input(0x02)
+            .build();
+
+        assert_eq!(
+            expected_input_reports(&report),
+            Err(InputError::InvalidDelimiter)
+        );
+    }
+
+    #[test]
+    fn open_delimiter_at_main_item_is_err() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .delimiter(1)
+            .usage(GenericDesktopControlsUsage::X)
+            .usage(GenericDesktopControlsUsage::Y)
+            .report_size(8)
+            .report_count(1)
+            .input(0x02)
+            .build();
+
+        assert_eq!(
+            expected_input_reports(&report),
+            Err(InputError::InvalidDelimiter)
+        );
+    }
+
+    #[test]
+    fn usage_range_in_delimiter_set_is_alternate() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::Button)
+            .delimiter(1)
+            .usage_minimum(1u16)
+            .usage_maximum(2u16)
+            .usage_minimum(5u16)
+            .usage_maximum(6u16)
+            .delimiter(0)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .report_size(1)
+            .report_count(2)
+            .input(0x02)
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let usages: Vec<&Usage> = expected.reports()[0]
+            .fields()
+            .iter()
+            .filter_map(|field| match field {
+                ExpectedField::Variable(item) => Some(item.usage()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(usages, vec![&Usage::Button(1), &Usage::Button(2)]);
+    }
+
+    #[test]
+    fn string_index_range_per_control() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::Button)
+            .usage_minimum::<u16>(1)
+            .usage_maximum::<u16>(3)
+            .string_minimum(10)
+            .string_maximum(11)
+            .designator_index(5)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .report_size(1)
+            .report_count(3)
+            .input(0x02)
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let indices: Vec<(Option<u32>, Option<u32>)> = expected.reports()[0]
+            .fields()
+            .iter()
+            .map(|field| match field {
+                ExpectedField::Variable(item) => (item.string_index(), item.designator_index()),
+                field => panic!("Expected a variable field, got {:?}", field),
+            })
+            .collect();
+
+        assert_eq!(
+            indices,
+            vec![
+                (Some(10), Some(5)),
+                (Some(11), Some(5)),
+                (Some(11), Some(5))
+            ]
+        );
+    }
+
+    #[test]
+    fn last_usage_applies_to_remaining_controls() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::X)
+            .usage(GenericDesktopControlsUsage::Y)
+            .report_size(8)
+            .report_count(3)
+            .input(0x02)
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let usages: Vec<&Usage> = expected.reports()[0]
+            .fields()
+            .iter()
+            .filter_map(|field| match field {
+                ExpectedField::Variable(item) => Some(item.usage()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            usages,
+            vec![
+                &Usage::GenericDesktopControls(GenericDesktopControlsUsage::X),
+                &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Y),
+                &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Y)
+            ]
+        );
+    }
+
+    #[test]
+    fn last_string_index_applies_to_remaining_controls() {
+        let report = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::X)
+            .usage(GenericDesktopControlsUsage::Y)
+            .usage(GenericDesktopControlsUsage::Z)
+            .string_index(4)
+            .string_index(7)
+            .report_size(8)
+            .report_count(3)
+            .input(0x02)
+            .build();
+
+        let expected = expected_input_reports(&report).unwrap();
+        let indices: Vec<Option<u32>> = expected.reports()[0]
+            .fields()
+            .iter()
+            .map(|field| match field {
+                ExpectedField::Variable(item) => item.string_index(),
+                field => panic!("Expected a variable field, got {:?}", field),
+            })
+            .collect();
+
+        assert_eq!(indices, vec![Some(4), Some(7), Some(7)]);
+    }
+
     #[test]
     fn parse_simple_report() {
         let expected_reports = ExpectedReports::from((
@@ -436,6 +1342,7 @@ mod tests {
                     index_in_raw: 0,
                     size_bits: 1,
                     options: Default::default(),
+                    ..Default::default()
                 })],
             }],
         ));
@@ -447,10 +1354,14 @@ mod tests {
                 usage: Usage::Button(1),
                 value: 1,
                 options: Default::default(),
+                out_of_range: false,
+                physical_value: 1.0,
+                unit: None,
+                alternate_usages: vec![],
             })],
         };
 
-        let result = parse_raw_input_report(&vec![1], &expected_reports);
+        let result = parse_raw_input_report(&[1], &expected_reports);
         assert_eq!(result, Ok(expected_result));
     }
 }
@@ -1,5 +1,6 @@
 use bitstream_io::{BitRead, BitReader, LittleEndian};
 use std::io::Cursor;
+use thiserror::Error;
 
 /// Parse the report_id from the first byte of HID Report
 pub(super) fn report_id(input: &[u8]) -> Result<u8, ParseError> {
@@ -27,15 +28,31 @@ pub(super) fn val(input: &[u8], position: u32, count: u32) -> Result<i64, ParseE
     Ok(result)
 }
 
+/// Sign-extend a value of `count` bits to an i64
+pub(super) fn sign_extend(value: i64, count: u32) -> i64 {
+    if count == 0 || count >= 64 {
+        return value;
+    }
+
+    let shift = 64 - count;
+    (value << shift) >> shift
+}
+
 /// Create a BitReader
 fn create_bit_reader(input: &[u8]) -> BitReader<Cursor<&[u8]>, LittleEndian> {
     let cursor = Cursor::new(input);
     BitReader::endian(cursor, LittleEndian)
 }
 
-#[derive(Debug, PartialEq)]
-pub(super) enum ParseError {
+/// Error while reading the bits of a report
+#[derive(Debug, PartialEq, Error)]
+pub enum ParseError {
+    /// The report ends before the given bit position
+    #[error("Can not skip to bit {0} of the report")]
     SkippingBitsFailed(u32),
+
+    /// The report ends before the given number of bits could be read
+    #[error("Can not read {0} bits from the report")]
     ReadingBitsFailed(u32),
 }
 
@@ -55,6 +72,12 @@ mod tests {
         assert_eq!(val(&report, 0, 4), Ok(0));
     }
 
+    #[test]
+    fn sign_extend_12_bits() {
+        assert_eq!(sign_extend(0xffb, 12), -5);
+        assert_eq!(sign_extend(0x7ff, 12), 2047);
+    }
+
     #[test]
     fn take_12_bits_alt() {
         let report: Vec<u8> = vec![0xf0, 0x00];
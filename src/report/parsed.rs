@@ -1,5 +1,6 @@
 use crate::report::expected::ExpectedField;
 use crate::report::InputError;
+use crate::report_descriptor::unit::Unit;
 use crate::report_descriptor::DataFieldOptions;
 use crate::usage_table::{Usage, UsagePage};
 
@@ -27,15 +28,22 @@ pub enum Field {
 
     /// An array value that is zero
     ArrayZeroValue(ArrayZeroItem),
+
+    /// A non-zero array value that does not select any of the declared usages
+    ArrayNoUsage(ArrayNoUsageItem),
 }
 
 /// A parsed variable value in a report
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Default)]
 pub struct VarItem {
     pub(crate) usage_page: UsagePage, // From Report Descriptor
     pub(crate) usage: Usage,          // From Report Descriptor
     pub(crate) value: i64,            // From RawReport
     pub(super) options: DataFieldOptions,
+    pub(crate) out_of_range: bool,
+    pub(crate) physical_value: f64,
+    pub(crate) unit: Option<Unit>,
+    pub(crate) alternate_usages: Vec<Usage>,
 }
 
 /// A parsed array value in a report
@@ -53,6 +61,101 @@ pub struct ArrayZeroItem {
     options: DataFieldOptions,
 }
 
+/// A parsed array value in a report that does not select a usage
+///
+/// This happens when the value lies outside the logical range, or when fewer usages
+/// were declared than the logical range allows.
+#[derive(Debug, PartialEq)]
+pub struct ArrayNoUsageItem {
+    pub(crate) usage_page: UsagePage,
+    pub(crate) value: i64,
+    options: DataFieldOptions,
+}
+
+impl ParsedReport {
+    /// Get the Report ID, if the report has one
+    pub fn report_id(&self) -> Option<u8> {
+        self.report_id
+    }
+
+    /// Get all parsed fields
+    pub fn fields(&self) -> &Vec<Field> {
+        &self.fields
+    }
+}
+
+impl VarItem {
+    /// Get the Usage Page
+    pub fn usage_page(&self) -> &UsagePage {
+        &self.usage_page
+    }
+
+    /// Get the Usage
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// Get the alternate usages of the control, if it was declared in a Delimiter set
+    pub fn alternate_usages(&self) -> &Vec<Usage> {
+        &self.alternate_usages
+    }
+
+    /// Get the logical value, sign-extended if the field is signed
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Get the data field options
+    pub fn options(&self) -> &DataFieldOptions {
+        &self.options
+    }
+
+    /// Returns true if the value is outside the Logical Minimum and Maximum
+    ///
+    /// For controls with a null state this means the control is not sending
+    /// meaningful data.
+    pub fn is_out_of_range(&self) -> bool {
+        self.out_of_range
+    }
+
+    /// Get the value converted to a physical quantity
+    ///
+    /// The value is expressed in the base units of the unit system of the field (see
+    /// [`VarItem::unit`]), for example centimetres for a length in SI Linear.
+    pub fn physical_value(&self) -> f64 {
+        self.physical_value
+    }
+
+    /// Get the unit of the physical value, if the field declared one
+    pub fn unit(&self) -> Option<&Unit> {
+        self.unit.as_ref()
+    }
+}
+
+impl ArrayValueItem {
+    /// Get the Usage Page of the selected usage
+    pub fn usage_page(&self) -> &UsagePage {
+        &self.usage_page
+    }
+
+    /// Get the selected Usage
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+}
+
+impl ArrayNoUsageItem {
+    /// Get the Usage Page of the array
+    pub fn usage_page(&self) -> &UsagePage {
+        &self.usage_page
+    }
+
+    /// Get the raw value of the array item
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+}
+
 impl TryFrom<(&ExpectedField, i64)> for Field {
     type Error = InputError;
 
@@ -63,12 +166,13 @@ impl TryFrom<(&ExpectedField, i64)> for Field {
             (ExpectedField::Variable(_), val) => {
                 Field::Variable(VarItem::try_from((value.0, val))?)
             }
-            (ExpectedField::ArrayItem(_), 0) => {
-                Field::ArrayZeroValue(ArrayZeroItem::try_from(value.0)?)
-            }
-            (ExpectedField::ArrayItem(_), _) => {
-                Field::ArrayValue(ArrayValueItem::try_from((value.0, value.1))?)
-            }
+            (ExpectedField::ArrayItem(item), val) => match item.array_usage(val) {
+                Some((_, id)) if id != 0 => {
+                    Field::ArrayValue(ArrayValueItem::try_from((value.0, val))?)
+                }
+                _ if val == 0 => Field::ArrayZeroValue(ArrayZeroItem::try_from(value.0)?),
+                _ => Field::ArrayNoUsage(ArrayNoUsageItem::try_from((value.0, val))?),
+            },
         };
 
         Ok(field)
@@ -85,6 +189,10 @@ impl TryFrom<(&ExpectedField, i64)> for VarItem {
                 usage: item.usage.clone(),
                 value: value.1,
                 options: item.options.clone(),
+                out_of_range: item.is_out_of_range(value.1),
+                physical_value: item.physical_value(value.1),
+                unit: item.unit.clone(),
+                alternate_usages: item.alternate_usages.clone(),
             }),
             _ => Err(InputError::VariableItemExpected),
         }
@@ -97,11 +205,13 @@ impl TryFrom<(&ExpectedField, i64)> for ArrayValueItem {
     fn try_from(value: (&ExpectedField, i64)) -> Result<Self, Self::Error> {
         match value.0 {
             ExpectedField::ArrayItem(item) => {
-                let usage_id = u16::try_from(value.1).map_err(|_| InputError::InvalidPayload)?;
+                let (usage_page, usage_id) = item
+                    .array_usage(value.1)
+                    .ok_or(InputError::ArrayValueWithoutUsage)?;
 
                 Ok(ArrayValueItem {
-                    usage_page: item.usage_page.clone(),
-                    usage: Usage::from((&item.usage_page, usage_id)),
+                    usage: Usage::from((&usage_page, usage_id)),
+                    usage_page,
                     options: item.options.clone(),
                 })
             }
@@ -110,6 +220,21 @@ impl TryFrom<(&ExpectedField, i64)> for ArrayValueItem {
     }
 }
 
+impl TryFrom<(&ExpectedField, i64)> for ArrayNoUsageItem {
+    type Error = InputError;
+
+    fn try_from(value: (&ExpectedField, i64)) -> Result<Self, Self::Error> {
+        match value.0 {
+            ExpectedField::ArrayItem(item) => Ok(ArrayNoUsageItem {
+                usage_page: item.usage_page.clone(),
+                value: value.1,
+                options: item.options.clone(),
+            }),
+            _ => Err(InputError::ArrayItemExpected),
+        }
+    }
+}
+
 impl TryFrom<&ExpectedField> for ArrayZeroItem {
     type Error = InputError;
 
@@ -127,7 +252,7 @@ impl TryFrom<&ExpectedField> for ArrayZeroItem {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::report::expected::{ExpectedField, ExpectedFieldItem};
+    use crate::report::expected::{ExpectedField, ExpectedFieldItem, UsageRange};
     use crate::usage_table::generic_desktop::GenericDesktopControlsUsage;
     use crate::usage_table::keyboard::KeyboardUsage;
     use crate::usage_table::{Usage, UsagePage};
@@ -140,6 +265,8 @@ mod tests {
             index_in_raw: 0,
             size_bits: 1,
             options: Default::default(),
+            array_usages: vec![UsageRange::from((UsagePage::Keyboard, 0x00, 0x91))],
+            ..Default::default()
         });
 
         let result = ArrayValueItem::try_from((&expected_field, 0x12));
@@ -162,6 +289,7 @@ mod tests {
             index_in_raw: 0,
             size_bits: 1,
             options: Default::default(),
+            ..Default::default()
         });
 
         let result = VarItem::try_from((&expected_field, 0x12));
@@ -172,8 +300,30 @@ mod tests {
                 usage_page: UsagePage::GenericDesktopControls,
                 usage: Usage::GenericDesktopControls(GenericDesktopControlsUsage::X),
                 value: 0x12,
-                options: Default::default()
+                options: Default::default(),
+                out_of_range: false,
+                physical_value: 18.0,
+                unit: None,
+                alternate_usages: vec![],
             })
         );
     }
+
+    #[test]
+    fn var_item_out_of_range() {
+        let expected_field = ExpectedField::Variable(ExpectedFieldItem {
+            usage_page: UsagePage::GenericDesktopControls,
+            usage: Usage::GenericDesktopControls(GenericDesktopControlsUsage::Wheel),
+            size_bits: 8,
+            logical_minimum: Some(-127),
+            logical_maximum: Some(127),
+            ..Default::default()
+        });
+
+        let result = VarItem::try_from((&expected_field, -128)).unwrap();
+        assert!(result.is_out_of_range());
+
+        let result = VarItem::try_from((&expected_field, -127)).unwrap();
+        assert!(!result.is_out_of_range());
+    }
 }
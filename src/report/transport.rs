@@ -0,0 +1,303 @@
+use crate::report::{expected_reports, InputError, ReportType};
+use crate::report_descriptor::ReportDescriptor;
+
+/// The byte length of one report
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ReportLength {
+    report_type: ReportType,
+    report_id: Option<u8>,
+    length: usize,
+}
+
+/// A transport a device sends its reports over, with its limits on the report length
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TransportProfile {
+    /// USB full-speed, interrupt endpoints with a max packet size of 64 bytes
+    UsbFullSpeed,
+
+    /// USB high-speed, interrupt endpoints with a max packet size of 1024 bytes
+    UsbHighSpeed,
+
+    /// Bluetooth LE HID over GATT. A report is one characteristic value. Input reports
+    /// are notified and must fit in ATT_MTU - 3 bytes, Output and Feature reports are
+    /// written and read with long writes and reads, up to 512 bytes. The Report ID is
+    /// not sent, it is part of the Report Reference descriptor.
+    BleHogp {
+        /// The negotiated ATT_MTU, 23 by default
+        mtu: u16,
+    },
+
+    /// HID over I2C. Reports are prefixed with a two byte length, the longest Input
+    /// report (with length) is announced in the HID descriptor.
+    I2cHid {
+        /// The wMaxInputLength of the HID descriptor
+        max_input_length: u16,
+    },
+}
+
+/// A report that is too long for a transport
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LengthViolation {
+    report: ReportLength,
+    profile: TransportProfile,
+    transferred: usize,
+    limit: usize,
+}
+
+/// Get the byte length of every Input, Output and Feature report, including the
+/// Report ID
+///
+/// # Example
+/// ```
+/// use hid_tools::report::transport::report_lengths;
+/// use hid_tools::report_builder::ReportDescriptorBuilder;
+///
+/// let descriptor = ReportDescriptorBuilder::new()
+///     .usage_page(0xff00u16)
+///     .report_id(1)
+///     .usage(0x01u16)
+///     .report_size(8)
+///     .report_count(63)
+///     .input(0x02)
+///     .build();
+///
+/// let lengths = report_lengths(&descriptor).unwrap();
+/// assert_eq!(lengths[0].report_id(), Some(1));
+/// assert_eq!(lengths[0].length(), 64);
+/// ```
+pub fn report_lengths(descriptor: &ReportDescriptor) -> Result<Vec<ReportLength>, InputError> {
+    let mut lengths: Vec<ReportLength> = Vec::new();
+
+    for report_type in [ReportType::Input, ReportType::Output, ReportType::Feature] {
+        let reports = expected_reports(descriptor, report_type)?;
+        lengths.extend(reports.reports().iter().map(|report| ReportLength {
+            report_type,
+            report_id: report.report_id(),
+            length: report.size().div_ceil(8),
+        }));
+    }
+
+    Ok(lengths)
+}
+
+/// Check the length of every report against the limits of a transport
+///
+/// Returns the reports that do not fit, in the order of [`report_lengths`].
+///
+/// # Example
+/// ```
+/// use hid_tools::report::transport::{check_report_lengths, TransportProfile};
+/// use hid_tools::report_builder::ReportDescriptorBuilder;
+///
+/// let descriptor = ReportDescriptorBuilder::new()
+///     .usage_page(0xff00u16)
+///     .report_id(1)
+///     .usage(0x01u16)
+///     .report_size(8)
+///     .report_count(64)
+///     .input(0x02)
+///     .build();
+///
+/// let violations = check_report_lengths(&descriptor, TransportProfile::UsbFullSpeed).unwrap();
+/// assert_eq!(
+///     violations[0].to_string(),
+///     "Input report 1 is 65 bytes, the limit of USB full-speed is 64 bytes"
+/// );
+///
+/// let violations = check_report_lengths(&descriptor, TransportProfile::UsbHighSpeed).unwrap();
+/// assert!(violations.is_empty());
+/// ```
+pub fn check_report_lengths(
+    descriptor: &ReportDescriptor,
+    profile: TransportProfile,
+) -> Result<Vec<LengthViolation>, InputError> {
+    Ok(report_lengths(descriptor)?
+        .into_iter()
+        .filter_map(|report| {
+            let limit = profile.limit(report.report_type)?;
+            let transferred = profile.transferred_length(&report);
+
+            (transferred > limit).then_some(LengthViolation {
+                report,
+                profile,
+                transferred,
+                limit,
+            })
+        })
+        .collect())
+}
+
+impl ReportLength {
+    /// Get the type of the report
+    pub fn report_type(&self) -> ReportType {
+        self.report_type
+    }
+
+    /// Get the Report ID, if any
+    pub fn report_id(&self) -> Option<u8> {
+        self.report_id
+    }
+
+    /// Get the length of the report in bytes, including the Report ID
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+impl TransportProfile {
+    /// Get the maximum number of bytes the transport carries for one report of the
+    /// given type, or None if it doesn't limit that report type
+    ///
+    /// Feature reports are sent with control transfers on USB, so the packet size of
+    /// the interrupt endpoints doesn't apply to them. On BLE only Input reports are
+    /// limited by the ATT_MTU, other reports by the maximum attribute value length. On
+    /// I2C only Input reports are limited by the HID descriptor, other reports by the
+    /// two byte length prefix.
+    pub fn limit(&self, report_type: ReportType) -> Option<usize> {
+        match (self, report_type) {
+            (TransportProfile::UsbFullSpeed, ReportType::Feature)
+            | (TransportProfile::UsbHighSpeed, ReportType::Feature) => None,
+            (TransportProfile::UsbFullSpeed, _) => Some(64),
+            (TransportProfile::UsbHighSpeed, _) => Some(1024),
+            (TransportProfile::BleHogp { mtu }, ReportType::Input) => {
+                Some(usize::from(*mtu).saturating_sub(3))
+            }
+            (TransportProfile::BleHogp { .. }, _) => Some(512),
+            (TransportProfile::I2cHid { max_input_length }, ReportType::Input) => {
+                Some(usize::from(*max_input_length))
+            }
+            (TransportProfile::I2cHid { .. }, _) => Some(usize::from(u16::MAX)),
+        }
+    }
+
+    /// Get the number of bytes the transport needs for a report
+    pub fn transferred_length(&self, report: &ReportLength) -> usize {
+        match self {
+            TransportProfile::UsbFullSpeed | TransportProfile::UsbHighSpeed => report.length,
+            TransportProfile::BleHogp { .. } => match report.report_id {
+                Some(_) => report.length - 1,
+                None => report.length,
+            },
+            TransportProfile::I2cHid { .. } => report.length + 2,
+        }
+    }
+}
+
+impl LengthViolation {
+    /// Get the report that is too long
+    pub fn report(&self) -> &ReportLength {
+        &self.report
+    }
+
+    /// Get the transport the report was checked against
+    pub fn profile(&self) -> TransportProfile {
+        self.profile
+    }
+
+    /// Get the number of bytes the transport needs for the report
+    pub fn transferred(&self) -> usize {
+        self.transferred
+    }
+
+    /// Get the maximum number of bytes the transport carries for the report
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report_builder::ReportDescriptorBuilder;
+
+    fn descriptor(report_count: u32) -> ReportDescriptor {
+        ReportDescriptorBuilder::new()
+            .usage_page(0xff00u16)
+            .report_id(1)
+            .usage(0x01u16)
+            .report_size(8)
+            .report_count(report_count)
+            .input(0x02)
+            .output(0x02)
+            .feature(0x02)
+            .build()
+    }
+
+    #[test]
+    fn lengths_round_up_to_bytes() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .usage_page(0x01u16)
+            .usage(0x30u16)
+            .report_size(12)
+            .report_count(1)
+            .input(0x02)
+            .build();
+
+        assert_eq!(
+            report_lengths(&descriptor).unwrap(),
+            vec![ReportLength {
+                report_type: ReportType::Input,
+                report_id: None,
+                length: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn usb_does_not_limit_feature_reports() {
+        let violations = check_report_lengths(&descriptor(100), TransportProfile::UsbFullSpeed)
+            .unwrap()
+            .iter()
+            .map(|v| v.report().report_type())
+            .collect::<Vec<ReportType>>();
+
+        assert_eq!(violations, vec![ReportType::Input, ReportType::Output]);
+    }
+
+    #[test]
+    fn ble_does_not_send_report_id() {
+        let profile = TransportProfile::BleHogp { mtu: 23 };
+
+        assert!(check_report_lengths(&descriptor(20), profile)
+            .unwrap()
+            .is_empty());
+
+        let violations = check_report_lengths(&descriptor(21), profile).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].transferred(), 21);
+        assert_eq!(violations[0].limit(), 20);
+    }
+
+    #[test]
+    fn ble_limits_feature_reports_to_attribute_length() {
+        let profile = TransportProfile::BleHogp { mtu: 23 };
+        let feature_violations = |report_count| {
+            check_report_lengths(&descriptor(report_count), profile)
+                .unwrap()
+                .into_iter()
+                .filter(|v| v.report().report_type() == ReportType::Feature)
+                .collect::<Vec<LengthViolation>>()
+        };
+
+        assert!(feature_violations(512).is_empty());
+
+        let violations = feature_violations(513);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].transferred(), 513);
+        assert_eq!(violations[0].limit(), 512);
+    }
+
+    #[test]
+    fn i2c_adds_length_prefix() {
+        let profile = TransportProfile::I2cHid {
+            max_input_length: 32,
+        };
+
+        let violations = check_report_lengths(&descriptor(30), profile).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].to_string(),
+            "Input report 1 is 33 bytes, the limit of I2C-HID is 32 bytes"
+        );
+    }
+}
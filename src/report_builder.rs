@@ -1,9 +1,14 @@
 use crate::report_descriptor::data::SizedPayload;
+use crate::report_descriptor::unit::Unit;
 use crate::report_descriptor::{
-    Collection, GlobalType, ItemType, LocalType, MainType, ReportDescriptor, ReportDescriptorItem,
+    Collection, GlobalType, ItemError, ItemType, Lint, LocalType, MainType, ReportDescriptor,
+    ReportDescriptorItem, Severity,
 };
 use crate::usage_table::{UsageId, UsagePage};
 
+/// Declarative builder for collections and fields
+pub mod typed;
+
 /// A builder for a ReportDescriptor
 ///
 /// # Example
@@ -30,6 +35,15 @@ pub struct ReportDescriptorBuilder {
     items: Vec<ReportDescriptorItem>,
 }
 
+/// Violations found by [`ReportDescriptorBuilder::try_build`]
+///
+/// Holds every [`Lint`] with [`Severity::Error`], in order of the items.
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+#[error("{}", join_lints(.lints))]
+pub struct BuildError {
+    lints: Vec<Lint>,
+}
+
 impl ReportDescriptorBuilder {
     /// Create a new DescriptorReportBuilder
     pub fn new() -> Self {
@@ -39,33 +53,54 @@ impl ReportDescriptorBuilder {
     /* MAIN ITEMS */
 
     /// Add Input item
+    ///
+    /// Accepts a raw payload or
+    /// [`DataFieldOptions`](crate::report_descriptor::DataFieldOptions), for example
+    /// `Mutability::Data | Structure::Variable | Value::Relative`.
+    ///
+    /// # Example
+    /// ```
+    /// use hid_tools::report_builder::ReportDescriptorBuilder;
+    /// use hid_tools::report_descriptor::{Data, Mutability, Structure, Value};
+    ///
+    /// let descriptor = ReportDescriptorBuilder::new()
+    ///     .input(Mutability::Data | Structure::Variable | Value::Relative)
+    ///     .input(Structure::Variable | Data::BufferedBytes)
+    ///     .build();
+    ///
+    /// assert_eq!(descriptor.bytes(), vec![0x81, 0x06, 0x82, 0x02, 0x01]);
+    /// ```
     pub fn input<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Main(MainType::Input);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Output item
+    ///
+    /// Accepts a raw payload or data field options, see [`ReportDescriptorBuilder::input`]
     pub fn output<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Main(MainType::Output);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Feature item
+    ///
+    /// Accepts a raw payload or data field options, see [`ReportDescriptorBuilder::input`]
     pub fn feature<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Main(MainType::Feature);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add a Collection item
     pub fn collection<T: Into<u8>>(self, collection: T) -> Self {
         let kind = ItemType::Main(MainType::Collection);
-        self.item_with_payload(kind, SizedPayload::from(collection.into()))
+        self.defined_item(kind, SizedPayload::from(collection.into()))
     }
 
     /// Add an End Collection item
     pub fn end_collection(self) -> Self {
         let kind = ItemType::Main(MainType::EndCollection);
-        self.item_with_payload(kind, SizedPayload::Empty)
+        self.defined_item(kind, SizedPayload::Empty)
     }
 
     /* GLOBAL ITEMS */
@@ -73,72 +108,75 @@ impl ReportDescriptorBuilder {
     /// Add Usage Page item
     pub fn usage_page<T: Into<u16>>(self, usage_page: T) -> Self {
         let kind = ItemType::Global(GlobalType::UsagePage);
-        self.item_with_payload(kind, SizedPayload::from(usage_page.into()))
+        self.defined_item(kind, SizedPayload::from(usage_page.into()))
     }
 
     /// Add Logical Minimum item
     pub fn logical_minimum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::LogicalMinimum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Logical Maximum item
     pub fn logical_maximum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::LogicalMaximum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
     /// Add Physical Minimum item
     pub fn physical_minimum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::PhysicalMinimum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Physical Maximum item
     pub fn physical_maximum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::PhysicalMaximum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Unit Exponent item
     pub fn unit_exponent<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::UnitExponent);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Unit item
-    pub fn unit<T: Into<u8>>(self, value: T) -> Self {
+    ///
+    /// Accepts a raw payload or a [`Unit`], for example
+    /// `Unit::new(UnitSystem::SILinear).with_length(1).with_time(-2)` for cm·s⁻².
+    pub fn unit<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::Unit);
-        self.item_with_payload(kind, value.into())
+        self.defined_item(kind, value)
     }
 
     /// Add Report Size item
     pub fn report_size<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::ReportSize);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Report ID item
     pub fn report_id<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::ReportID);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Report Count item
     pub fn report_count<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::ReportCount);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Push item
     pub fn push<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::Push);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Pop item
     pub fn pop<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Global(GlobalType::Pop);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /* LOCAL ITEMS */
@@ -146,61 +184,97 @@ impl ReportDescriptorBuilder {
     /// Add an Usage item
     pub fn usage<T: Into<u16>>(self, usage: T) -> Self {
         let kind = ItemType::Local(LocalType::Usage);
-        self.item_with_payload(kind, SizedPayload::from(usage.into()))
+        self.defined_item(kind, SizedPayload::from(usage.into()))
     }
 
     /// Add an Usage Minimum item
     pub fn usage_minimum<T: Into<u16>>(self, usage: T) -> Self {
         let kind = ItemType::Local(LocalType::UsageMinimum);
-        self.item_with_payload(kind, SizedPayload::from(usage.into()))
+        self.defined_item(kind, SizedPayload::from(usage.into()))
     }
 
     /// Add an Usage Maximum item
     pub fn usage_maximum<T: Into<u16>>(self, usage: T) -> Self {
         let kind = ItemType::Local(LocalType::UsageMaximum);
-        self.item_with_payload(kind, SizedPayload::from(usage.into()))
+        self.defined_item(kind, SizedPayload::from(usage.into()))
+    }
+
+    /// Add an extended Usage item. The usage page is encoded in the high word
+    /// of the four byte payload and overrides the global Usage Page.
+    pub fn extended_usage<P: Into<u16>, T: Into<u16>>(self, usage_page: P, usage: T) -> Self {
+        let kind = ItemType::Local(LocalType::Usage);
+        self.defined_item(
+            kind,
+            extended_usage_payload(usage_page.into(), usage.into()),
+        )
+    }
+
+    /// Add an extended Usage Minimum item
+    pub fn extended_usage_minimum<P: Into<u16>, T: Into<u16>>(
+        self,
+        usage_page: P,
+        usage: T,
+    ) -> Self {
+        let kind = ItemType::Local(LocalType::UsageMinimum);
+        self.defined_item(
+            kind,
+            extended_usage_payload(usage_page.into(), usage.into()),
+        )
+    }
+
+    /// Add an extended Usage Maximum item
+    pub fn extended_usage_maximum<P: Into<u16>, T: Into<u16>>(
+        self,
+        usage_page: P,
+        usage: T,
+    ) -> Self {
+        let kind = ItemType::Local(LocalType::UsageMaximum);
+        self.defined_item(
+            kind,
+            extended_usage_payload(usage_page.into(), usage.into()),
+        )
     }
 
     /// Add Designator Index item
     pub fn designator_index<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Local(LocalType::DesignatorIndex);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Designator Minimum item
     pub fn designator_minimum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Local(LocalType::DesignatorMinimum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Designator Maximum item
     pub fn designator_maximum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Local(LocalType::DesignatorMaximum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add String Index item
     pub fn string_index<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Local(LocalType::StringIndex);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add String Minimum item
     pub fn string_minimum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Local(LocalType::StringMinimum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add String Maximum item
     pub fn string_maximum<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Local(LocalType::StringMaximum);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /// Add Delimiter item
     pub fn delimiter<T: Into<SizedPayload>>(self, value: T) -> Self {
         let kind = ItemType::Local(LocalType::Delimiter);
-        self.item_with_payload(kind, value)
+        self.defined_item(kind, value)
     }
 
     /* HELPERS */
@@ -212,14 +286,28 @@ impl ReportDescriptorBuilder {
         self
     }
 
+    /// Add an item of a type the typed methods above cover
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload is a [`SizedPayload::Long`], which only long items carry.
+    fn defined_item<T: Into<SizedPayload>>(self, kind: ItemType, payload: T) -> Self {
+        let item = ReportDescriptorItem::try_from((kind, payload.into()))
+            .expect("only long items can have a long payload");
+        self.push_item(item)
+    }
+
     /// Add an item with payload
-    pub fn item_with_payload<T, U>(self, item_type: T, payload: U) -> Self
+    ///
+    /// Returns an error if the item can not be encoded, for example a reserved item type
+    /// with a tag that is used by a defined item, see [`ItemError`].
+    pub fn item_with_payload<T, U>(self, item_type: T, payload: U) -> Result<Self, ItemError>
     where
         T: Into<ItemType>,
         U: Into<SizedPayload>,
     {
-        let item = ReportDescriptorItem::from((item_type.into(), payload.into()));
-        self.push_item(item)
+        let item = ReportDescriptorItem::try_from((item_type.into(), payload.into()))?;
+        Ok(self.push_item(item))
     }
 
     /// Add an item
@@ -230,14 +318,73 @@ impl ReportDescriptorBuilder {
         let kind: ItemType = item.clone().into();
         let payload: SizedPayload = item.into();
 
-        let item = ReportDescriptorItem::from((kind, payload));
-        self.push_item(item)
+        self.defined_item(kind, payload)
     }
 
     /// Build ReportDescriptor
     pub fn build(self) -> ReportDescriptor {
         ReportDescriptor::new(self.items)
     }
+
+    /// Build ReportDescriptor, if the items form a valid descriptor
+    ///
+    /// The built descriptor is checked with [`ReportDescriptor::lint`]. Violations with
+    /// [`Severity::Error`] make the build fail, warnings are allowed.
+    ///
+    /// # Example
+    /// ```
+    /// use hid_tools::report_builder::ReportDescriptorBuilder;
+    /// use hid_tools::report_descriptor::{Collection, Rule};
+    ///
+    /// let result = ReportDescriptorBuilder::new()
+    ///     .usage_page(0x01u16)
+    ///     .usage(0x02u16)
+    ///     .collection(Collection::Application)
+    ///     .report_count(3)
+    ///     .input(0x02)
+    ///     .end_collection()
+    ///     .try_build();
+    ///
+    /// let lints = result.unwrap_err();
+    /// assert_eq!(lints.lints()[0].rule(), Rule::MissingReportSize);
+    /// assert_eq!(lints.lints()[0].index(), 4);
+    /// ```
+    pub fn try_build(self) -> Result<ReportDescriptor, BuildError> {
+        let descriptor = self.build();
+        let lints: Vec<Lint> = descriptor
+            .lint()
+            .into_iter()
+            .filter(|lint| lint.severity() == Severity::Error)
+            .collect();
+
+        match lints.is_empty() {
+            true => Ok(descriptor),
+            false => Err(BuildError { lints }),
+        }
+    }
+}
+
+impl BuildError {
+    /// Get the violations, at least one
+    pub fn lints(&self) -> &[Lint] {
+        &self.lints
+    }
+}
+
+/// Display the lints of a BuildError, separated by "; "
+fn join_lints(lints: &[Lint]) -> String {
+    lints
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Encode an extended usage. The payload is always four bytes, even if the
+/// value would fit in less, as the size is what marks the usage as extended.
+fn extended_usage_payload(usage_page: u16, usage: u16) -> SizedPayload {
+    let value = (u32::from(usage_page) << 16) | u32::from(usage);
+    SizedPayload::Four(value.to_le_bytes())
 }
 
 /*
@@ -279,6 +426,12 @@ impl<T: UsageId> From<T> for ItemType {
     }
 }
 
+impl From<Unit> for ItemType {
+    fn from(_: Unit) -> Self {
+        ItemType::Global(GlobalType::Unit)
+    }
+}
+
 impl From<Collection> for ItemType {
     fn from(_: Collection) -> Self {
         ItemType::Main(MainType::Collection)
@@ -289,7 +442,8 @@ impl From<Collection> for ItemType {
 mod tests {
     use super::*;
     use crate::report_descriptor::data::Size;
-    use crate::report_descriptor::{GlobalType, ItemType, ReportDescriptorItem};
+    use crate::report_descriptor::unit::UnitSystem;
+    use crate::report_descriptor::{GlobalType, ItemType, ReportDescriptorItem, Rule};
     use crate::usage_table::keyboard::KeyboardUsage;
     use crate::usage_table::{Usage, UsagePage};
 
@@ -375,6 +529,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn extended_usage_is_always_four_bytes() {
+        let result = ReportDescriptorBuilder::new().extended_usage(UsagePage::Consumer, 0x0238u16);
+
+        assert_eq!(
+            result,
+            ReportDescriptorBuilder {
+                items: vec![ReportDescriptorItem {
+                    kind: ItemType::Local(LocalType::Usage),
+                    payload_size: Size::Four,
+                    raw: vec![0x0b, 0x38, 0x02, 0x0c, 0x00]
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn unit_multi_nibble() {
+        let unit = Unit::new(UnitSystem::SILinear).with_length(1).with_time(-2);
+        let result = ReportDescriptorBuilder::new().unit(unit);
+
+        assert_eq!(
+            result,
+            ReportDescriptorBuilder {
+                items: vec![ReportDescriptorItem {
+                    kind: ItemType::Global(GlobalType::Unit),
+                    payload_size: Size::Two,
+                    raw: vec![0x66, 0x11, 0xe0]
+                }],
+            }
+        );
+    }
+
     #[test]
     fn logical_minimum_negative() {
         let result = ReportDescriptorBuilder::new().logical_minimum(-1);
@@ -390,4 +577,149 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn long_item_data_is_not_truncated() {
+        let result = ReportDescriptorBuilder::new()
+            .item_with_payload(ItemType::Long(0xf1), SizedPayload::Long(vec![0; 300]));
+
+        assert_eq!(result, Err(ItemError::LongDataTooLarge(300)));
+    }
+
+    #[test]
+    fn long_item_with_largest_data() {
+        let bytes = ReportDescriptorBuilder::new()
+            .item_with_payload(ItemType::Long(0xf1), SizedPayload::Long(vec![0; 255]))
+            .unwrap()
+            .build()
+            .bytes();
+
+        assert_eq!(bytes[..3], [0xfe, 0xff, 0xf1]);
+        assert_eq!(bytes.len(), 258);
+    }
+
+    #[test]
+    fn long_payload_on_short_item() {
+        let result = ReportDescriptorBuilder::new().item_with_payload(
+            ItemType::Global(GlobalType::ReportSize),
+            SizedPayload::Long(vec![8]),
+        );
+
+        assert_eq!(result, Err(ItemError::LongPayload));
+    }
+
+    #[test]
+    fn reserved_item_with_defined_tag() {
+        let result = ReportDescriptorBuilder::new()
+            .item_with_payload(ItemType::Global(GlobalType::Reserved(0x4)), [0x01]);
+
+        assert_eq!(
+            result,
+            Err(ItemError::TagNotReserved {
+                kind: ItemType::Global(GlobalType::Reserved(0x4)),
+                size: Size::One
+            })
+        );
+    }
+
+    #[test]
+    fn try_build_valid_descriptor() {
+        let builder = || {
+            ReportDescriptorBuilder::new()
+                .usage_page(UsagePage::GenericDesktopControls)
+                .usage(0x02u16)
+                .collection(Collection::Application)
+                .push(SizedPayload::Empty)
+                .report_size(8)
+                .report_count(1)
+                .usage(0x30u16)
+                .input(0x06)
+                .pop(SizedPayload::Empty)
+                .end_collection()
+        };
+
+        assert_eq!(builder().try_build(), Ok(builder().build()));
+    }
+
+    fn error_rules(error: &BuildError) -> Vec<(Rule, usize)> {
+        error
+            .lints()
+            .iter()
+            .map(|lint| (lint.rule(), lint.index()))
+            .collect()
+    }
+
+    #[test]
+    fn try_build_unbalanced_collections() {
+        let result = ReportDescriptorBuilder::new()
+            .collection(Collection::Application)
+            .end_collection()
+            .end_collection()
+            .try_build();
+        assert_eq!(
+            error_rules(&result.unwrap_err()),
+            vec![(Rule::UnbalancedCollection, 2)]
+        );
+
+        let result = ReportDescriptorBuilder::new()
+            .collection(Collection::Application)
+            .collection(Collection::Physical)
+            .end_collection()
+            .try_build();
+        assert_eq!(
+            error_rules(&result.unwrap_err()),
+            vec![(Rule::UnbalancedCollection, 0)]
+        );
+    }
+
+    #[test]
+    fn try_build_globals_restored_by_pop() {
+        let result = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .report_size(8)
+            .push(SizedPayload::Empty)
+            .report_count(1)
+            .pop(SizedPayload::Empty)
+            .feature(0x02)
+            .try_build();
+
+        assert_eq!(
+            error_rules(&result.unwrap_err()),
+            vec![(Rule::MissingReportCount, 5)]
+        );
+    }
+
+    #[test]
+    fn try_build_reports_every_error() {
+        let error = ReportDescriptorBuilder::new()
+            .pop(SizedPayload::Empty)
+            .end_collection()
+            .usage(0x30u16)
+            .try_build()
+            .unwrap_err();
+
+        assert_eq!(
+            error_rules(&error),
+            vec![(Rule::PopWithoutPush, 0), (Rule::UnbalancedCollection, 1)]
+        );
+        assert_eq!(
+            error.to_string(),
+            "error[pop-without-push] item 0: Pop without a preceding Push; \
+             error[unbalanced-collection] item 1: End Collection without an open Collection"
+        );
+    }
+
+    #[test]
+    fn try_build_allows_warnings() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .report_size(8)
+            .report_count(1)
+            .input(0x01)
+            .usage(0x30u16)
+            .usage(0x31u16)
+            .try_build()
+            .unwrap();
+
+        assert_eq!(descriptor.lint()[0].rule(), Rule::DanglingLocalItems);
+    }
 }
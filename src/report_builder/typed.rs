@@ -0,0 +1,595 @@
+use crate::report::ReportType;
+use crate::report_builder::ReportDescriptorBuilder;
+use crate::report_descriptor::{
+    Collection as CollectionType, DataFieldOptions, Mutability, ReportDescriptor, Structure, Value,
+};
+use crate::usage_table::UsagePage;
+use std::ops::RangeInclusive;
+
+/// A Report Descriptor described by its collections and fields
+///
+/// The item stream is derived from the description: global items are only emitted
+/// when their value changes, fields are padded to byte boundaries and, if there is
+/// more than one top-level collection, every top-level collection gets a Report ID.
+///
+/// # Example
+/// ```
+/// use hid_tools::report_builder::typed::{Collection, Descriptor, Field};
+/// use hid_tools::usage_table::UsagePage;
+/// use hid_tools::usage_table::generic_desktop::GenericDesktopControlsUsage;
+///
+/// let descriptor = Descriptor::new()
+///     .with(
+///         Collection::application(UsagePage::GenericDesktopControls, GenericDesktopControlsUsage::Mouse)
+///             .with(
+///                 Collection::physical(UsagePage::GenericDesktopControls, GenericDesktopControlsUsage::Pointer)
+///                     .with(Field::variable(UsagePage::Button, 1..=3))
+///                     .with(
+///                         Field::variables(
+///                             UsagePage::GenericDesktopControls,
+///                             [GenericDesktopControlsUsage::X, GenericDesktopControlsUsage::Y],
+///                         )
+///                         .bits(8)
+///                         .logical(-127..=127)
+///                         .relative(),
+///                     ),
+///             ),
+///     )
+///     .build();
+///
+/// // The boot protocol mouse of the HID specification (appendix E.10)
+/// assert_eq!(
+///     descriptor.bytes(),
+///     vec![
+///         0x05, 0x01, 0x09, 0x02, 0xa1, 0x01, 0x09, 0x01, 0xa1, 0x00, 0x05, 0x09, 0x19, 0x01,
+///         0x29, 0x03, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x03, 0x81, 0x02, 0x75, 0x05,
+///         0x95, 0x01, 0x81, 0x01, 0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7f,
+///         0x75, 0x08, 0x95, 0x02, 0x81, 0x06, 0xc0, 0xc0,
+///     ]
+/// );
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Descriptor {
+    collections: Vec<Collection>,
+}
+
+/// A collection with its usage, nested collections and fields
+#[derive(Debug, PartialEq, Clone)]
+pub struct Collection {
+    kind: CollectionType,
+    usage_page: UsagePage,
+    usage: u16,
+    report_id: Option<u8>,
+    members: Vec<Member>,
+}
+
+/// A member of a collection
+#[derive(Debug, PartialEq, Clone)]
+pub enum Member {
+    /// A nested collection
+    Collection(Collection),
+
+    /// A data field
+    Field(Field),
+}
+
+/// One or more controls of the same size and logical range, added with one
+/// Input, Output or Feature item
+#[derive(Debug, PartialEq, Clone)]
+pub struct Field {
+    kind: FieldKind,
+    report_type: ReportType,
+    bits: Option<u32>,
+    logical: Option<(i32, i32)>,
+    relative: bool,
+}
+
+/// The kind of field, with the usages it reports
+#[derive(Debug, PartialEq, Clone)]
+enum FieldKind {
+    /// One value per usage
+    Variable(UsagePage, Usages),
+
+    /// Slots that each hold the usage ID of an active control
+    Array(UsagePage, RangeInclusive<u16>, u32),
+
+    /// Constant bits
+    Padding(u32),
+}
+
+/// Usages of variable fields, listed one by one or as a range
+#[derive(Debug, PartialEq, Clone)]
+enum Usages {
+    List(Vec<u16>),
+    Range(RangeInclusive<u16>),
+}
+
+impl Descriptor {
+    /// Create an empty description
+    pub fn new() -> Self {
+        Descriptor::default()
+    }
+
+    /// Add a top-level collection
+    pub fn with(mut self, collection: Collection) -> Self {
+        self.collections.push(collection);
+        self
+    }
+
+    /// Emit the items on a ReportDescriptorBuilder, to add more items by hand
+    pub fn builder(self) -> ReportDescriptorBuilder {
+        let report_ids = self.report_ids();
+        let mut emitter = Emitter::default();
+
+        for (collection, report_id) in self.collections.iter().zip(report_ids) {
+            emitter.top_level_collection(collection, report_id);
+        }
+
+        emitter.builder
+    }
+
+    /// Build the ReportDescriptor
+    pub fn build(self) -> ReportDescriptor {
+        self.builder().build()
+    }
+
+    /// Get the Report ID of each top-level collection. Explicit Report IDs are kept,
+    /// the others get the lowest free ID, starting at 1.
+    fn report_ids(&self) -> Vec<Option<u8>> {
+        let explicit = self.collections.iter().any(|c| c.report_id.is_some());
+        if self.collections.len() <= 1 && !explicit {
+            return vec![None; self.collections.len()];
+        }
+
+        let mut used: Vec<u8> = self
+            .collections
+            .iter()
+            .filter_map(|c| c.report_id)
+            .collect();
+
+        self.collections
+            .iter()
+            .map(|collection| {
+                collection.report_id.or_else(|| {
+                    let free = (1..=u8::MAX).find(|id| !used.contains(id))?;
+                    used.push(free);
+                    Some(free)
+                })
+            })
+            .collect()
+    }
+}
+
+impl From<Collection> for Descriptor {
+    fn from(value: Collection) -> Self {
+        Descriptor::new().with(value)
+    }
+}
+
+impl Collection {
+    /// Create a collection of the given type
+    pub fn new<P: Into<UsagePage>, U: Into<u16>>(
+        kind: CollectionType,
+        usage_page: P,
+        usage: U,
+    ) -> Self {
+        Collection {
+            kind,
+            usage_page: usage_page.into(),
+            usage: usage.into(),
+            report_id: None,
+            members: Vec::new(),
+        }
+    }
+
+    /// Create an Application collection
+    pub fn application<P: Into<UsagePage>, U: Into<u16>>(usage_page: P, usage: U) -> Self {
+        Collection::new(CollectionType::Application, usage_page, usage)
+    }
+
+    /// Create a Physical collection
+    pub fn physical<P: Into<UsagePage>, U: Into<u16>>(usage_page: P, usage: U) -> Self {
+        Collection::new(CollectionType::Physical, usage_page, usage)
+    }
+
+    /// Create a Logical collection
+    pub fn logical<P: Into<UsagePage>, U: Into<u16>>(usage_page: P, usage: U) -> Self {
+        Collection::new(CollectionType::Logical, usage_page, usage)
+    }
+
+    /// Add a nested collection or a field
+    pub fn with<T: Into<Member>>(mut self, member: T) -> Self {
+        self.members.push(member.into());
+        self
+    }
+
+    /// Set the Report ID of the reports in this collection. Only used for top-level
+    /// collections.
+    pub fn report_id(mut self, report_id: u8) -> Self {
+        self.report_id = Some(report_id);
+        self
+    }
+}
+
+impl From<Collection> for Member {
+    fn from(value: Collection) -> Self {
+        Member::Collection(value)
+    }
+}
+
+impl From<Field> for Member {
+    fn from(value: Field) -> Self {
+        Member::Field(value)
+    }
+}
+
+impl Field {
+    fn new(kind: FieldKind) -> Self {
+        Field {
+            kind,
+            report_type: ReportType::Input,
+            bits: None,
+            logical: None,
+            relative: false,
+        }
+    }
+
+    /// Create a variable field with one control per usage in the range
+    ///
+    /// The controls are one bit (0 or 1) unless set with [`Field::bits`].
+    pub fn variable<P: Into<UsagePage>>(usage_page: P, usages: RangeInclusive<u16>) -> Self {
+        Field::new(FieldKind::Variable(
+            usage_page.into(),
+            Usages::Range(usages),
+        ))
+    }
+
+    /// Create a variable field with one control per listed usage
+    pub fn variables<P, U, I>(usage_page: P, usages: I) -> Self
+    where
+        P: Into<UsagePage>,
+        U: Into<u16>,
+        I: IntoIterator<Item = U>,
+    {
+        let usages = usages.into_iter().map(Into::into).collect();
+        Field::new(FieldKind::Variable(usage_page.into(), Usages::List(usages)))
+    }
+
+    /// Create an array field with a number of slots, each holding the usage ID of an
+    /// active control in the range
+    ///
+    /// The slots are 8 bits, or 16 bits for usage IDs above 255, unless set with
+    /// [`Field::bits`].
+    pub fn array<P: Into<UsagePage>>(
+        usage_page: P,
+        usages: RangeInclusive<u16>,
+        slots: u32,
+    ) -> Self {
+        Field::new(FieldKind::Array(usage_page.into(), usages, slots))
+    }
+
+    /// Create constant padding bits
+    pub fn padding(bits: u32) -> Self {
+        Field::new(FieldKind::Padding(bits))
+    }
+
+    /// Set the size of each control in bits (Report Size)
+    pub fn bits(mut self, bits: u32) -> Self {
+        self.bits = Some(bits);
+        self
+    }
+
+    /// Set the logical range of the values. Defaults to the unsigned range of the size
+    /// for variable fields and to the usage IDs for array fields.
+    pub fn logical(mut self, range: RangeInclusive<i32>) -> Self {
+        self.logical = Some((*range.start(), *range.end()));
+        self
+    }
+
+    /// Mark the values as relative to the previous report, like mouse movement
+    pub fn relative(mut self) -> Self {
+        self.relative = true;
+        self
+    }
+
+    /// Put the field in the Output report
+    pub fn output(mut self) -> Self {
+        self.report_type = ReportType::Output;
+        self
+    }
+
+    /// Put the field in the Feature report
+    pub fn feature(mut self) -> Self {
+        self.report_type = ReportType::Feature;
+        self
+    }
+
+    /// Get the size of each control in bits
+    fn size(&self) -> u32 {
+        match (&self.kind, self.bits) {
+            (FieldKind::Padding(bits), _) => *bits,
+            (_, Some(bits)) => bits,
+            (FieldKind::Variable(..), None) => 1,
+            (FieldKind::Array(_, usages, _), None) if *usages.end() <= 0xff => 8,
+            (FieldKind::Array(..), None) => 16,
+        }
+    }
+
+    /// Get the number of controls
+    fn count(&self) -> u32 {
+        match &self.kind {
+            FieldKind::Variable(_, Usages::List(usages)) => usages.len() as u32,
+            FieldKind::Variable(_, Usages::Range(usages)) => {
+                (u32::from(*usages.end()) + 1).saturating_sub(u32::from(*usages.start()))
+            }
+            FieldKind::Array(_, _, slots) => *slots,
+            FieldKind::Padding(_) => 1,
+        }
+    }
+
+    /// Get the logical range of the values
+    fn logical_range(&self) -> (i32, i32) {
+        match (&self.kind, self.logical) {
+            (_, Some(range)) => range,
+            (FieldKind::Array(_, usages, _), None) => {
+                (i32::from(*usages.start()), i32::from(*usages.end()))
+            }
+            _ => {
+                let maximum = (1i64 << self.size().min(32)) - 1;
+                (0, i32::try_from(maximum).unwrap_or(i32::MAX))
+            }
+        }
+    }
+}
+
+/// Global items in effect and report lengths while emitting items
+#[derive(Default)]
+struct Emitter {
+    builder: ReportDescriptorBuilder,
+    usage_page: Option<u16>,
+    logical: Option<(i32, i32)>,
+    report_size: Option<u32>,
+    report_count: Option<u32>,
+    report_id: Option<u8>,
+    bits: [u32; 3], // Length of the Input, Output and Feature report in bits
+}
+
+impl Emitter {
+    fn top_level_collection(&mut self, collection: &Collection, report_id: Option<u8>) {
+        self.bits = [0; 3];
+
+        self.collection_start(collection);
+        if let Some(report_id) = report_id.filter(|id| self.report_id != Some(*id)) {
+            self.builder = std::mem::take(&mut self.builder).report_id(report_id);
+            self.report_id = Some(report_id);
+        }
+
+        self.members(collection);
+
+        // Every report ends on a byte boundary
+        for report_type in [ReportType::Input, ReportType::Output, ReportType::Feature] {
+            self.align(report_type);
+        }
+        self.builder = std::mem::take(&mut self.builder).end_collection();
+    }
+
+    fn collection_start(&mut self, collection: &Collection) {
+        self.set_usage_page(&collection.usage_page);
+        self.builder = std::mem::take(&mut self.builder)
+            .usage(collection.usage)
+            .collection(collection.kind.clone());
+    }
+
+    fn members(&mut self, collection: &Collection) {
+        for member in &collection.members {
+            match member {
+                Member::Collection(nested) => {
+                    self.collection_start(nested);
+                    self.members(nested);
+                    self.builder = std::mem::take(&mut self.builder).end_collection();
+                }
+                Member::Field(field) => self.field(field),
+            }
+        }
+    }
+
+    fn field(&mut self, field: &Field) {
+        // Multi-byte values start on a byte boundary
+        if field.size() % 8 == 0 {
+            self.align(field.report_type);
+        }
+
+        let options = match &field.kind {
+            FieldKind::Variable(usage_page, usages) => {
+                self.set_usage_page(usage_page);
+                let builder = std::mem::take(&mut self.builder);
+                self.builder = match usages {
+                    Usages::List(usages) => usages.iter().fold(builder, |b, u| b.usage(*u)),
+                    Usages::Range(usages) => builder
+                        .usage_minimum(*usages.start())
+                        .usage_maximum(*usages.end()),
+                };
+                Mutability::Data | Structure::Variable
+            }
+            FieldKind::Array(usage_page, usages, _) => {
+                self.set_usage_page(usage_page);
+                self.builder = std::mem::take(&mut self.builder)
+                    .usage_minimum(*usages.start())
+                    .usage_maximum(*usages.end());
+                Mutability::Data | Structure::Array
+            }
+            FieldKind::Padding(_) => DataFieldOptions::from(Mutability::Constant),
+        };
+
+        if !matches!(field.kind, FieldKind::Padding(_)) {
+            self.set_logical(field.logical_range());
+        }
+        let options = match field.relative {
+            true => options | Value::Relative,
+            false => options,
+        };
+
+        self.main(field.report_type, field.size(), field.count(), options);
+    }
+
+    /// Add constant bits up to the next byte boundary of the report
+    fn align(&mut self, report_type: ReportType) {
+        let bits = self.bits[report_index(report_type)] % 8;
+        if bits != 0 {
+            self.main(report_type, 8 - bits, 1, Mutability::Constant.into());
+        }
+    }
+
+    fn main(&mut self, report_type: ReportType, size: u32, count: u32, options: DataFieldOptions) {
+        if self.report_size != Some(size) {
+            self.builder = std::mem::take(&mut self.builder).report_size(size);
+            self.report_size = Some(size);
+        }
+        if self.report_count != Some(count) {
+            self.builder = std::mem::take(&mut self.builder).report_count(count);
+            self.report_count = Some(count);
+        }
+
+        let builder = std::mem::take(&mut self.builder);
+        self.builder = match report_type {
+            ReportType::Input => builder.input(options),
+            ReportType::Output => builder.output(options),
+            ReportType::Feature => builder.feature(options),
+        };
+        self.bits[report_index(report_type)] += size * count;
+    }
+
+    fn set_usage_page(&mut self, usage_page: &UsagePage) {
+        let usage_page = u16::from(usage_page.clone());
+        if self.usage_page != Some(usage_page) {
+            self.builder = std::mem::take(&mut self.builder).usage_page(usage_page);
+            self.usage_page = Some(usage_page);
+        }
+    }
+
+    fn set_logical(&mut self, (minimum, maximum): (i32, i32)) {
+        let (current_minimum, current_maximum) = match self.logical {
+            Some((minimum, maximum)) => (Some(minimum), Some(maximum)),
+            None => (None, None),
+        };
+
+        if current_minimum != Some(minimum) {
+            self.builder = std::mem::take(&mut self.builder).logical_minimum(minimum);
+        }
+        if current_maximum != Some(maximum) {
+            self.builder = std::mem::take(&mut self.builder).logical_maximum(maximum);
+        }
+        self.logical = Some((minimum, maximum));
+    }
+}
+
+fn report_index(report_type: ReportType) -> usize {
+    match report_type {
+        ReportType::Input => 0,
+        ReportType::Output => 1,
+        ReportType::Feature => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{expected_input_reports, expected_output_reports};
+    use crate::usage_table::consumer::ConsumerUsage;
+    use crate::usage_table::generic_desktop::GenericDesktopControlsUsage;
+    use crate::usage_table::keyboard::KeyboardUsage;
+
+    fn keyboard() -> Collection {
+        Collection::application(
+            UsagePage::GenericDesktopControls,
+            GenericDesktopControlsUsage::Keyboard,
+        )
+        .with(Field::variable(UsagePage::Keyboard, 0xe0..=0xe7))
+        .with(Field::variable(UsagePage::LED, 1..=5).output())
+        .with(Field::array(UsagePage::Keyboard, 0x00..=0x65, 6))
+    }
+
+    #[test]
+    fn boot_keyboard() {
+        let descriptor = Descriptor::from(keyboard()).build();
+
+        assert!(descriptor.lint().is_empty());
+        assert_eq!(
+            descriptor.bytes(),
+            vec![
+                0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00,
+                0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05,
+                0x95, 0x05, 0x91, 0x02, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x25, 0x65, 0x75, 0x08,
+                0x95, 0x06, 0x81, 0x00, 0x75, 0x03, 0x95, 0x01, 0x91, 0x01, 0xc0,
+            ]
+        );
+    }
+
+    #[test]
+    fn logical_range_of_31_bits() {
+        let field = Field::variable(UsagePage::VendorDefined(0xff00), 0x01..=0x01).bits(31);
+
+        assert_eq!(field.logical_range(), (0, i32::MAX));
+    }
+
+    #[test]
+    fn logical_range_of_32_bits() {
+        let field = Field::variable(UsagePage::VendorDefined(0xff00), 0x01..=0x01).bits(32);
+
+        assert_eq!(field.logical_range(), (0, i32::MAX));
+    }
+
+    #[test]
+    fn reports_are_byte_aligned() {
+        let descriptor = Descriptor::from(keyboard()).build();
+
+        let input = expected_input_reports(&descriptor).unwrap();
+        let output = expected_output_reports(&descriptor).unwrap();
+        assert_eq!(input.reports()[0].size(), 56);
+        assert_eq!(output.reports()[0].size(), 8);
+    }
+
+    #[test]
+    fn report_ids_are_assigned() {
+        let descriptor = Descriptor::new()
+            .with(keyboard())
+            .with(
+                Collection::application(UsagePage::Consumer, ConsumerUsage::ConsumerControl)
+                    .with(Field::array(UsagePage::Consumer, 0x00..=0x29c, 1)),
+            )
+            .with(
+                Collection::application(UsagePage::VendorDefined(0xff00), 0x01u16)
+                    .report_id(1)
+                    .with(Field::variables(UsagePage::VendorDefined(0xff00), [0x01u16]).bits(8)),
+            )
+            .build();
+
+        let ids: Vec<Vec<u8>> = descriptor
+            .application_collections()
+            .iter()
+            .map(|a| a.report_ids().into_iter().collect())
+            .collect();
+        assert_eq!(ids, vec![vec![2], vec![3], vec![1]]);
+
+        let input = expected_input_reports(&descriptor).unwrap();
+        assert_eq!(input.find_report(Some(3)).unwrap().size(), 24);
+    }
+
+    #[test]
+    fn consecutive_padding_shares_globals() {
+        let descriptor = Descriptor::from(
+            Collection::application(UsagePage::Keyboard, KeyboardUsage::KeyboardLeftControl)
+                .with(Field::padding(4).feature())
+                .with(Field::padding(4).feature()),
+        )
+        .build();
+
+        assert_eq!(
+            descriptor.bytes(),
+            vec![
+                0x05, 0x07, 0x09, 0xe0, 0xa1, 0x01, 0x75, 0x04, 0x95, 0x01, 0xb1, 0x01, 0xb1, 0x01,
+                0xc0
+            ]
+        );
+    }
+}
@@ -1,11 +1,15 @@
-use crate::report_descriptor::{GlobalType, ItemType, LocalType, MainType};
+use crate::report_descriptor::unit::Unit;
+use crate::report_descriptor::{
+    DataFieldOptions, GlobalType, ItemError, ItemType, LocalType, MainType,
+};
 
 /// Payload data in the Report Descriptor
 ///
-/// Payload can be short (zero, one, two or four bytes) or long (unimplemented)
-#[derive(Clone, Debug, PartialEq)]
+/// Payload can be short (zero, one, two or four bytes) or long (up to 255 bytes)
+#[derive(Clone, Debug, PartialEq, Default)]
 pub enum SizedPayload {
     /// Zero sized payload
+    #[default]
     Empty,
 
     /// Payload size of one byte
@@ -16,13 +20,17 @@ pub enum SizedPayload {
 
     /// Payload size of four bytes
     Four([u8; 4]),
-    // Long(Vec<u8>)
+
+    /// Data of a long item, at most 255 bytes
+    Long(Vec<u8>),
 }
 
 impl SizedPayload {
     /// Get the size of the payload
-    pub fn size(&self) -> Size {
-        Size::from(self)
+    ///
+    /// Will return None if long item data is longer than 255 bytes
+    pub fn size(&self) -> Option<Size> {
+        Size::try_from(self).ok()
     }
 
     /// Get a reference to the payload data
@@ -34,6 +42,8 @@ impl SizedPayload {
             SizedPayload::One(data) => Some(&data[..]),
             SizedPayload::Two(data) => Some(&data[..]),
             SizedPayload::Four(data) => Some(&data[..]),
+            SizedPayload::Long(data) if data.is_empty() => None,
+            SizedPayload::Long(data) => Some(&data[..]),
         }
     }
 
@@ -44,6 +54,7 @@ impl SizedPayload {
             SizedPayload::One(p) => p.to_vec(),
             SizedPayload::Two(p) => p.to_vec(),
             SizedPayload::Four(p) => p.to_vec(),
+            SizedPayload::Long(p) => p,
         }
     }
 
@@ -119,6 +130,23 @@ impl From<i32> for SizedPayload {
     }
 }
 
+impl From<Unit> for SizedPayload {
+    fn from(value: Unit) -> Self {
+        SizedPayload::from(u32::from(value))
+    }
+}
+
+impl From<DataFieldOptions> for SizedPayload {
+    /// One byte, or two bytes if Buffered Bytes (bit 8) is set
+    fn from(value: DataFieldOptions) -> Self {
+        let [low, high] = value.bits().to_le_bytes();
+        match high {
+            0 => SizedPayload::One([low]),
+            _ => SizedPayload::Two([low, high]),
+        }
+    }
+}
+
 impl From<[u8; 0]> for SizedPayload {
     fn from(_: [u8; 0]) -> Self {
         SizedPayload::Empty
@@ -195,6 +223,7 @@ impl From<SizedPayload> for i32 {
             SizedPayload::One(data) => i8::from_le_bytes(data).into(),
             SizedPayload::Two(data) => i16::from_le_bytes(data).into(),
             SizedPayload::Four(data) => i32::from_le_bytes(data),
+            SizedPayload::Long(data) => i32::from_le_bytes(first_four_bytes(&data)),
         }
     }
 }
@@ -206,17 +235,21 @@ impl From<SizedPayload> for u32 {
             SizedPayload::One(data) => u8::from_le_bytes(data).into(),
             SizedPayload::Two(data) => u16::from_le_bytes(data).into(),
             SizedPayload::Four(data) => u32::from_le_bytes(data),
+            SizedPayload::Long(data) => u32::from_le_bytes(first_four_bytes(&data)),
         }
     }
 }
 
-impl Default for SizedPayload {
-    fn default() -> Self {
-        Self::Empty
-    }
+/// Take the first four bytes of long item data, padded with zeros
+fn first_four_bytes(data: &[u8]) -> [u8; 4] {
+    let mut bytes = [0u8; 4];
+    let len = data.len().min(4);
+    bytes[..len].copy_from_slice(&data[..len]);
+    bytes
 }
 
-/// The payload of an HID Report Descriptor Item can only be 0, 1, 2 or 4 bytes.
+/// The payload of a short HID Report Descriptor Item can only be 0, 1, 2 or 4 bytes.
+/// Long items carry their data size in the second byte.
 ///
 /// Note: a raw size of 0x3 means payload size 4
 #[derive(Debug, PartialEq, Clone)]
@@ -232,7 +265,9 @@ pub enum Size {
 
     /// Four bytes payload
     Four,
-    //Long(u8) unimplemented
+
+    /// Data size of a long item
+    Long(u8),
 }
 
 impl From<&[u8; 0]> for Size {
@@ -259,13 +294,16 @@ impl From<&[u8; 4]> for Size {
     }
 }
 
-impl From<&SizedPayload> for Size {
-    fn from(value: &SizedPayload) -> Self {
+impl TryFrom<&SizedPayload> for Size {
+    type Error = ();
+
+    fn try_from(value: &SizedPayload) -> Result<Self, Self::Error> {
         match value {
-            SizedPayload::Empty => Size::Empty,
-            SizedPayload::One(_) => Size::One,
-            SizedPayload::Two(_) => Size::Two,
-            SizedPayload::Four(_) => Size::Four,
+            SizedPayload::Empty => Ok(Size::Empty),
+            SizedPayload::One(_) => Ok(Size::One),
+            SizedPayload::Two(_) => Ok(Size::Two),
+            SizedPayload::Four(_) => Ok(Size::Four),
+            SizedPayload::Long(data) => u8::try_from(data.len()).map(Size::Long).map_err(|_| ()),
         }
     }
 }
@@ -291,22 +329,20 @@ impl PartialEq<usize> for Size {
             Size::One => *other == 1,
             Size::Two => *other == 2,
             Size::Four => *other == 4,
+            Size::Long(size) => *other == *size as usize,
         }
     }
 }
 
 impl PartialEq<Size> for usize {
     fn eq(&self, other: &Size) -> bool {
-        match self {
-            0 => Size::Empty == *other,
-            1 => Size::One == *other,
-            2 => Size::Two == *other,
-            4 => Size::Four == *other,
-            _ => false,
-        }
+        other == self
     }
 }
 
+/// The prefix byte of every long item. The data size and tag follow in the next two bytes.
+pub(crate) const LONG_ITEM_PREFIX: u8 = 0b11111110;
+
 /// PrefixByte is an indicator for the type of the Report Descriptor Item
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) struct PrefixByte(u8);
@@ -327,8 +363,19 @@ impl PrefixByte {
     }
 }
 
-impl From<(&ItemType, &Size)> for PrefixByte {
-    fn from(value: (&ItemType, &Size)) -> Self {
+impl TryFrom<(&ItemType, &Size)> for PrefixByte {
+    type Error = ItemError;
+
+    /// Fails if a reserved item type has a tag that is not reserved, see
+    /// `is_reserved_tag`.
+    fn try_from(value: (&ItemType, &Size)) -> Result<Self, Self::Error> {
+        if !is_reserved_tag(value.0, value.1) {
+            return Err(ItemError::TagNotReserved {
+                kind: value.0.clone(),
+                size: value.1.clone(),
+            });
+        }
+
         let prefix_mask: u8 = match value.0 {
             // Main hid items
             // https://www.usb.org/sites/default/files/hid1_11.pdf - page 28
@@ -337,6 +384,7 @@ impl From<(&ItemType, &Size)> for PrefixByte {
             ItemType::Main(MainType::Feature) => 0b10110000,
             ItemType::Main(MainType::Collection) => 0b10100000,
             ItemType::Main(MainType::EndCollection) => 0b11000000,
+            ItemType::Main(MainType::Reserved(tag)) => tag << 4,
 
             // Global hid items
             // https://www.usb.org/sites/default/files/hid1_11.pdf - page 35
@@ -352,6 +400,7 @@ impl From<(&ItemType, &Size)> for PrefixByte {
             ItemType::Global(GlobalType::ReportCount) => 0b10010100,
             ItemType::Global(GlobalType::Push) => 0b10100100,
             ItemType::Global(GlobalType::Pop) => 0b10110100,
+            ItemType::Global(GlobalType::Reserved(tag)) => tag << 4 | 0b0100,
 
             // Local hid items
             // https://www.usb.org/sites/default/files/hid1_11.pdf - page 39
@@ -365,6 +414,14 @@ impl From<(&ItemType, &Size)> for PrefixByte {
             ItemType::Local(LocalType::StringMinimum) => 0b10001000,
             ItemType::Local(LocalType::StringMaximum) => 0b10011000,
             ItemType::Local(LocalType::Delimiter) => 0b10101000,
+            ItemType::Local(LocalType::Reserved(tag)) => tag << 4 | 0b1000,
+
+            // Items of the reserved type
+            ItemType::Reserved(tag) => tag << 4 | 0b1100,
+
+            // Long items
+            // https://www.usb.org/sites/default/files/hid1_11.pdf - page 27
+            ItemType::Long(_) => LONG_ITEM_PREFIX,
         };
 
         let size_mask: u8 = match value.1 {
@@ -372,9 +429,26 @@ impl From<(&ItemType, &Size)> for PrefixByte {
             Size::One => 1,
             Size::Two => 2,
             Size::Four => 3, // Note: payload size = 0x03
+            Size::Long(_) => LONG_ITEM_PREFIX & 0x3,
         };
 
-        PrefixByte(prefix_mask | size_mask)
+        Ok(PrefixByte(prefix_mask | size_mask))
+    }
+}
+
+/// Determine if the tag of a reserved item type is reserved by the specification
+///
+/// Tags of defined items can not be used as reserved tags, and tag 0xf of the reserved
+/// item type with a two byte payload is the prefix of long items. Other item types are
+/// always valid.
+pub(crate) fn is_reserved_tag(kind: &ItemType, size: &Size) -> bool {
+    match kind {
+        ItemType::Main(MainType::Reserved(tag)) => matches!(tag, 0x0..=0x7 | 0xd..=0xf),
+        ItemType::Global(GlobalType::Reserved(tag)) => matches!(tag, 0xc..=0xf),
+        ItemType::Local(LocalType::Reserved(tag)) => matches!(tag, 0x6 | 0xb..=0xf),
+        ItemType::Reserved(0xf) => !matches!(size, Size::Two | Size::Long(_)),
+        ItemType::Reserved(tag) => *tag <= 0xf,
+        _ => true,
     }
 }
 
@@ -390,13 +464,46 @@ mod tests {
 
     #[test]
     fn usage_page_size_1() {
-        let result = PrefixByte::from((&ItemType::Global(GlobalType::UsagePage), &Size::One));
-        assert_eq!(result, PrefixByte(0b00000101));
+        let result = PrefixByte::try_from((&ItemType::Global(GlobalType::UsagePage), &Size::One));
+        assert_eq!(result, Ok(PrefixByte(0b00000101)));
     }
 
     #[test]
     fn usage_page_size_2() {
-        let result = PrefixByte::from((&ItemType::Global(GlobalType::UsagePage), &Size::Two));
-        assert_eq!(result, PrefixByte(0b00000110));
+        let result = PrefixByte::try_from((&ItemType::Global(GlobalType::UsagePage), &Size::Two));
+        assert_eq!(result, Ok(PrefixByte(0b00000110)));
+    }
+
+    #[test]
+    fn reserved_tags() {
+        assert!(is_reserved_tag(
+            &ItemType::Main(MainType::Reserved(0xd)),
+            &Size::One
+        ));
+        assert!(is_reserved_tag(&ItemType::Reserved(0xf), &Size::One));
+        assert!(!is_reserved_tag(
+            &ItemType::Main(MainType::Reserved(0x8)),
+            &Size::One
+        ));
+        assert!(!is_reserved_tag(
+            &ItemType::Global(GlobalType::Reserved(0x4)),
+            &Size::One
+        ));
+        assert!(!is_reserved_tag(
+            &ItemType::Local(LocalType::Reserved(0x10)),
+            &Size::One
+        ));
+    }
+
+    #[test]
+    fn reserved_tag_colliding_with_long_item() {
+        let result = PrefixByte::try_from((&ItemType::Reserved(0xf), &Size::Two));
+        assert_eq!(
+            result,
+            Err(ItemError::TagNotReserved {
+                kind: ItemType::Reserved(0xf),
+                size: Size::Two
+            })
+        );
     }
 }
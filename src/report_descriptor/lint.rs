@@ -0,0 +1,480 @@
+use crate::report::input::resolve_maximum;
+use crate::report_descriptor::data::SizedPayload;
+use crate::report_descriptor::{
+    GlobalType, ItemType, MainType, Mutability, ReportDescriptor, ReportDescriptorItem,
+};
+
+/// How serious a rule violation is
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Severity {
+    /// Allowed by the specification, but likely a mistake or handled differently by hosts
+    Warning,
+
+    /// Violates the specification, hosts may reject the descriptor
+    Error,
+}
+
+/// A rule a Report Descriptor is checked against
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Rule {
+    /// Every Collection needs a matching End Collection and vice versa
+    UnbalancedCollection,
+
+    /// An Input, Output or Feature item needs a Report Size
+    MissingReportSize,
+
+    /// An Input, Output or Feature item needs a Report Count
+    MissingReportCount,
+
+    /// A data (non constant) field needs a Usage Page
+    MissingUsagePage,
+
+    /// A Pop item needs a preceding Push item
+    PopWithoutPush,
+
+    /// Report ID 0 is reserved
+    ReportIdZero,
+
+    /// If any report has a Report ID, all reports need one
+    MixedReportId,
+
+    /// The Logical Minimum must not be greater than the Logical Maximum
+    LogicalMinimumAboveMaximum,
+
+    /// Values of a field should fit in 32 bits
+    FieldTooWide,
+
+    /// Local items only apply to the next main item, there must be one
+    DanglingLocalItems,
+}
+
+/// A rule violation found in a Report Descriptor
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lint {
+    rule: Rule,
+    index: usize,
+    explanation: String,
+}
+
+/// Global items in effect, as far as the rules need them
+#[derive(Default, Clone)]
+struct GlobalState {
+    usage_page: bool,
+    report_size: Option<u32>,
+    report_count: Option<u32>,
+    report_id: Option<u32>,
+    logical_minimum: Option<i32>,
+    logical_maximum: Option<SizedPayload>,
+    // Set when the current logical range has been checked, to report it only once
+    logical_range_checked: bool,
+}
+
+impl Rule {
+    /// Get the name of the rule, e.g. "unbalanced-collection"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UnbalancedCollection => "unbalanced-collection",
+            Rule::MissingReportSize => "missing-report-size",
+            Rule::MissingReportCount => "missing-report-count",
+            Rule::MissingUsagePage => "missing-usage-page",
+            Rule::PopWithoutPush => "pop-without-push",
+            Rule::ReportIdZero => "report-id-zero",
+            Rule::MixedReportId => "mixed-report-id",
+            Rule::LogicalMinimumAboveMaximum => "logical-minimum-above-maximum",
+            Rule::FieldTooWide => "field-too-wide",
+            Rule::DanglingLocalItems => "dangling-local-items",
+        }
+    }
+
+    /// Get the severity of a violation of the rule
+    pub fn severity(&self) -> Severity {
+        match self {
+            Rule::FieldTooWide | Rule::DanglingLocalItems => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl Lint {
+    fn new(rule: Rule, index: usize, explanation: impl Into<String>) -> Self {
+        Lint {
+            rule,
+            index,
+            explanation: explanation.into(),
+        }
+    }
+
+    /// Get the violated rule
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Get the severity of the violation
+    pub fn severity(&self) -> Severity {
+        self.rule.severity()
+    }
+
+    /// Get the index of the offending item in [`ReportDescriptor::items`]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get an explanation of the violation
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+}
+
+impl ReportDescriptor {
+    /// Check the descriptor against the rules of the HID specification
+    ///
+    /// Returns all violations, in order of the items. An empty list means the
+    /// descriptor passed every [`Rule`].
+    ///
+    /// # Example
+    /// ```
+    /// use hid_tools::report_builder::ReportDescriptorBuilder;
+    /// use hid_tools::report_descriptor::{Collection, Rule, Severity};
+    ///
+    /// let descriptor = ReportDescriptorBuilder::new()
+    ///     .usage_page(0x01u16)
+    ///     .collection(Collection::Application)
+    ///     .report_count(1)
+    ///     .input(0x02)
+    ///     .build();
+    ///
+    /// let lints = descriptor.lint();
+    /// assert_eq!(lints[0].rule(), Rule::UnbalancedCollection);
+    /// assert_eq!(lints[0].index(), 1);
+    /// assert_eq!(lints[1].rule(), Rule::MissingReportSize);
+    /// assert_eq!(lints[1].severity(), Severity::Error);
+    /// assert_eq!(
+    ///     lints[1].to_string(),
+    ///     "error[missing-report-size] item 3: Input item without a Report Size"
+    /// );
+    /// ```
+    pub fn lint(&self) -> Vec<Lint> {
+        let items = self.items();
+        let mut lints: Vec<Lint> = Vec::new();
+
+        let mut globals = GlobalState::default();
+        let mut stack: Vec<GlobalState> = Vec::new();
+        let mut collections: Vec<usize> = Vec::new();
+        let mut first_local: Option<usize> = None;
+
+        let uses_report_id = items.iter().any(|item| item.is_report_id());
+
+        for (index, item) in items.iter().enumerate() {
+            match &item.kind {
+                ItemType::Global(GlobalType::UsagePage) => globals.usage_page = true,
+                ItemType::Global(GlobalType::ReportSize) => {
+                    globals.report_size = Some(item.payload_u32());
+                }
+                ItemType::Global(GlobalType::ReportCount) => {
+                    globals.report_count = Some(item.payload_u32());
+                }
+                ItemType::Global(GlobalType::ReportID) => {
+                    let report_id = item.payload_u32();
+                    if report_id == 0 {
+                        lints.push(Lint::new(
+                            Rule::ReportIdZero,
+                            index,
+                            "Report ID 0 is reserved, Report IDs start at 1",
+                        ));
+                    }
+                    globals.report_id = Some(report_id);
+                }
+                ItemType::Global(GlobalType::LogicalMinimum) => {
+                    globals.logical_minimum = Some(item.payload_i32());
+                    globals.logical_range_checked = false;
+                }
+                ItemType::Global(GlobalType::LogicalMaximum) => {
+                    globals.logical_maximum = Some(item.raw_payload());
+                    globals.logical_range_checked = false;
+                }
+                ItemType::Global(GlobalType::Push) => stack.push(globals.clone()),
+                ItemType::Global(GlobalType::Pop) => match stack.pop() {
+                    Some(state) => globals = state,
+                    None => lints.push(Lint::new(
+                        Rule::PopWithoutPush,
+                        index,
+                        "Pop without a preceding Push",
+                    )),
+                },
+                ItemType::Local(_) => {
+                    first_local = first_local.or(Some(index));
+                }
+                ItemType::Main(MainType::Collection) => collections.push(index),
+                ItemType::Main(MainType::EndCollection) => {
+                    let collection = collections.pop();
+                    if collection.is_none() {
+                        lints.push(Lint::new(
+                            Rule::UnbalancedCollection,
+                            index,
+                            "End Collection without an open Collection",
+                        ));
+                    }
+                }
+                ItemType::Main(MainType::Input)
+                | ItemType::Main(MainType::Output)
+                | ItemType::Main(MainType::Feature) => {
+                    lint_data_item(item, index, &mut globals, uses_report_id, &mut lints);
+                }
+                _ => {}
+            }
+
+            if let ItemType::Main(_) = item.kind {
+                first_local = None;
+            }
+        }
+
+        for index in collections {
+            lints.push(Lint::new(
+                Rule::UnbalancedCollection,
+                index,
+                "Collection without End Collection",
+            ));
+        }
+
+        if let Some(index) = first_local {
+            lints.push(Lint::new(
+                Rule::DanglingLocalItems,
+                index,
+                "Local items at the end of the descriptor are not followed by a main item",
+            ));
+        }
+
+        lints.sort_by_key(|lint| lint.index);
+        lints
+    }
+}
+
+/// Check the global items in effect for an Input, Output or Feature item
+fn lint_data_item(
+    item: &ReportDescriptorItem,
+    index: usize,
+    globals: &mut GlobalState,
+    uses_report_id: bool,
+    lints: &mut Vec<Lint>,
+) {
+    let name = match &item.kind {
+        ItemType::Main(main_type) => main_type.to_string(),
+        _ => return,
+    };
+    let is_constant = item
+        .data_field_options()
+        .is_some_and(|options| options.mutability() == &Mutability::Constant);
+
+    match globals.report_size {
+        None => lints.push(Lint::new(
+            Rule::MissingReportSize,
+            index,
+            format!("{} item without a Report Size", name),
+        )),
+        Some(size) if size > 32 && !is_constant => lints.push(Lint::new(
+            Rule::FieldTooWide,
+            index,
+            format!(
+                "{} item with a Report Size of {} bits, values are limited to 32 bits",
+                name, size
+            ),
+        )),
+        _ => {}
+    }
+
+    if globals.report_count.is_none() {
+        lints.push(Lint::new(
+            Rule::MissingReportCount,
+            index,
+            format!("{} item without a Report Count", name),
+        ));
+    }
+
+    if !globals.usage_page && !is_constant {
+        lints.push(Lint::new(
+            Rule::MissingUsagePage,
+            index,
+            format!("{} item without a Usage Page", name),
+        ));
+    }
+
+    if uses_report_id && globals.report_id.is_none() {
+        lints.push(Lint::new(
+            Rule::MixedReportId,
+            index,
+            format!(
+                "{} item without a Report ID, while other reports have one",
+                name
+            ),
+        ));
+    }
+
+    if let (Some(minimum), Some(maximum), false) = (
+        globals.logical_minimum,
+        globals.logical_maximum.as_ref(),
+        globals.logical_range_checked,
+    ) {
+        let maximum = resolve_maximum(minimum, maximum);
+
+        if i64::from(minimum) > maximum {
+            lints.push(Lint::new(
+                Rule::LogicalMinimumAboveMaximum,
+                index,
+                format!(
+                    "{} item with Logical Minimum {} above Logical Maximum {}",
+                    name, minimum, maximum
+                ),
+            ));
+        }
+        globals.logical_range_checked = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report_builder::ReportDescriptorBuilder;
+    use crate::report_descriptor::parse::report_descriptor;
+    use crate::report_descriptor::Collection;
+
+    fn rules(descriptor: &ReportDescriptor) -> Vec<(Rule, usize)> {
+        descriptor
+            .lint()
+            .iter()
+            .map(|lint| (lint.rule(), lint.index()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_descriptor() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .usage_page(0x01u16)
+            .usage(0x02u16)
+            .collection(Collection::Application)
+            .report_id(1)
+            .logical_minimum(-127)
+            .logical_maximum(127)
+            .report_size(8)
+            .report_count(1)
+            .usage(0x30u16)
+            .input(0x06)
+            .end_collection()
+            .build();
+
+        assert_eq!(descriptor.lint(), vec![]);
+    }
+
+    #[test]
+    fn unbalanced_collections() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .end_collection()
+            .collection(Collection::Application)
+            .build();
+
+        assert_eq!(
+            rules(&descriptor),
+            vec![
+                (Rule::UnbalancedCollection, 0),
+                (Rule::UnbalancedCollection, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn pop_without_push() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .push(SizedPayload::Empty)
+            .pop(SizedPayload::Empty)
+            .pop(SizedPayload::Empty)
+            .build();
+
+        assert_eq!(rules(&descriptor), vec![(Rule::PopWithoutPush, 2)]);
+    }
+
+    #[test]
+    fn missing_globals() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .input(0x02)
+            .report_size(8)
+            .report_count(1)
+            .input(0x01)
+            .build();
+
+        assert_eq!(
+            rules(&descriptor),
+            vec![
+                (Rule::MissingReportSize, 0),
+                (Rule::MissingReportCount, 0),
+                (Rule::MissingUsagePage, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_ids() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .usage_page(0x01u16)
+            .report_size(8)
+            .report_count(1)
+            .input(0x02)
+            .report_id(0)
+            .input(0x02)
+            .build();
+
+        assert_eq!(
+            rules(&descriptor),
+            vec![(Rule::MixedReportId, 3), (Rule::ReportIdZero, 4)]
+        );
+    }
+
+    #[test]
+    fn logical_range_is_checked_once() {
+        // Logical Minimum 10, Logical Maximum 5, two Inputs
+        let descriptor = report_descriptor(&[
+            0x05, 0x01, 0x15, 0x0a, 0x25, 0x05, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0x81, 0x02,
+        ])
+        .unwrap();
+
+        let lints = descriptor.lint();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rule(), Rule::LogicalMinimumAboveMaximum);
+        assert_eq!(
+            lints[0].explanation(),
+            "Input item with Logical Minimum 10 above Logical Maximum 5"
+        );
+    }
+
+    #[test]
+    fn signed_logical_maximum() {
+        // Logical Maximum 0xfe is 254 with Logical Minimum 0, but -2 with -1
+        let descriptor = report_descriptor(&[
+            0x05, 0x01, 0x15, 0x00, 0x25, 0xfe, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0x15, 0xff,
+            0x81, 0x02,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            rules(&descriptor),
+            vec![(Rule::LogicalMinimumAboveMaximum, 7)]
+        );
+    }
+
+    #[test]
+    fn wide_fields_and_dangling_locals() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .usage_page(0x01u16)
+            .report_size(64)
+            .report_count(1)
+            .input(0x01)
+            .input(0x02)
+            .usage(0x30u16)
+            .build();
+
+        let lints = descriptor.lint();
+        assert_eq!(
+            rules(&descriptor),
+            vec![(Rule::FieldTooWide, 4), (Rule::DanglingLocalItems, 5)]
+        );
+        assert!(lints.iter().all(|l| l.severity() == Severity::Warning));
+    }
+}
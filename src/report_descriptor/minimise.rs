@@ -0,0 +1,237 @@
+use crate::error::Error;
+use crate::report::{expected_feature_reports, expected_input_reports, expected_output_reports};
+use crate::report_descriptor::data::SizedPayload;
+use crate::report_descriptor::{
+    GlobalType, ItemType, LocalType, MainType, ReportDescriptor, ReportDescriptorItem,
+};
+
+/// The meaning of an item payload, used to decide if a payload can be shrunk and if a
+/// global item repeats the current value
+#[derive(Debug, PartialEq, Clone)]
+enum Meaning {
+    /// Payload read as unsigned value
+    Unsigned(u32),
+
+    /// Payload read as signed (two's complement) value
+    Signed(i32),
+
+    /// Payload read as signed or unsigned value, depending on another item. The
+    /// Logical and Physical Maximum are signed only if their minimum is negative,
+    /// and not every host agrees on that, so both readings are kept.
+    Either(i32, u32),
+
+    /// Payload that is only valid with some sizes
+    Optional(Option<u32>),
+}
+
+impl ReportDescriptor {
+    /// Get an equivalent descriptor with as few bytes as possible
+    ///
+    /// Global items that set the value already in effect are removed and every payload
+    /// is shrunk to the smallest size that keeps its value. Signedness is respected: a
+    /// Logical Maximum of 255 keeps two bytes, because a single 0xff byte is -1 to hosts
+    /// that read it as signed. Four byte (extended) usages are kept as they are.
+    ///
+    /// The result is checked by comparing the Input, Output and Feature report layouts
+    /// of both descriptors.
+    ///
+    /// # Example
+    /// ```
+    /// use hid_tools::report_builder::ReportDescriptorBuilder;
+    ///
+    /// let descriptor = ReportDescriptorBuilder::new()
+    ///     .usage_page(0x01u16)
+    ///     .report_size([8, 0, 0, 0])
+    ///     .report_count(1)
+    ///     .usage(0x30u16)
+    ///     .input(0x02)
+    ///     .usage_page(0x01u16)
+    ///     .usage(0x31u16)
+    ///     .input(0x02)
+    ///     .build();
+    ///
+    /// let minimised = descriptor.minimise().unwrap();
+    /// assert_eq!(
+    ///     minimised.bytes(),
+    ///     vec![0x05, 0x01, 0x75, 0x08, 0x95, 0x01, 0x09, 0x30, 0x81, 0x02, 0x09, 0x31, 0x81, 0x02]
+    /// );
+    /// ```
+    pub fn minimise(&self) -> Result<ReportDescriptor, Error> {
+        let items: Vec<ReportDescriptorItem> = without_redundant_globals(self.items())
+            .into_iter()
+            .map(shrink)
+            .collect();
+        let minimised = ReportDescriptor::new(items);
+
+        if expected_input_reports(self)? != expected_input_reports(&minimised)?
+            || expected_output_reports(self)? != expected_output_reports(&minimised)?
+            || expected_feature_reports(self)? != expected_feature_reports(&minimised)?
+        {
+            return Err(Error::NotEquivalent);
+        }
+
+        Ok(minimised)
+    }
+}
+
+/// Get the meaning of the payload. Items without meaning (Push, Pop, long and
+/// reserved items) are never changed.
+fn meaning(item: &ReportDescriptorItem) -> Option<Meaning> {
+    match &item.kind {
+        ItemType::Global(GlobalType::UsagePage) => {
+            Some(Meaning::Optional(item.payload_u16().map(u32::from)))
+        }
+        ItemType::Global(GlobalType::ReportID) => Some(Meaning::Optional(
+            u8::try_from(item.raw_payload()).ok().map(u32::from),
+        )),
+        ItemType::Global(GlobalType::ReportSize)
+        | ItemType::Global(GlobalType::ReportCount)
+        | ItemType::Global(GlobalType::Unit) => Some(Meaning::Unsigned(item.payload_u32())),
+        ItemType::Global(GlobalType::LogicalMinimum)
+        | ItemType::Global(GlobalType::PhysicalMinimum) => {
+            Some(Meaning::Signed(item.payload_i32()))
+        }
+        ItemType::Global(GlobalType::LogicalMaximum)
+        | ItemType::Global(GlobalType::PhysicalMaximum) => {
+            Some(Meaning::Either(item.payload_i32(), item.payload_u32()))
+        }
+        ItemType::Global(GlobalType::UnitExponent) => item.unit_exponent().map(Meaning::Signed),
+        ItemType::Global(_) => None,
+        ItemType::Local(LocalType::Usage)
+        | ItemType::Local(LocalType::UsageMinimum)
+        | ItemType::Local(LocalType::UsageMaximum)
+            if item.is_extended_usage() =>
+        {
+            None
+        }
+        ItemType::Local(LocalType::Usage)
+        | ItemType::Local(LocalType::UsageMinimum)
+        | ItemType::Local(LocalType::UsageMaximum) => {
+            Some(Meaning::Optional(item.payload_u16().map(u32::from)))
+        }
+        ItemType::Local(LocalType::Reserved(_)) => None,
+        ItemType::Local(_) => Some(Meaning::Unsigned(item.payload_u32())),
+        ItemType::Main(MainType::Input)
+        | ItemType::Main(MainType::Output)
+        | ItemType::Main(MainType::Feature) => Some(Meaning::Optional(
+            item.data_field_options().map(|_| item.payload_u32()),
+        )),
+        ItemType::Main(MainType::Collection) => Some(Meaning::Optional(
+            item.collection().map(|_| item.payload_u32()),
+        )),
+        ItemType::Main(_) | ItemType::Long(_) | ItemType::Reserved(_) => None,
+    }
+}
+
+/// Remove global items that set the value already in effect
+fn without_redundant_globals(items: &[ReportDescriptorItem]) -> Vec<&ReportDescriptorItem> {
+    let mut state: Vec<(GlobalType, Meaning)> = Vec::new();
+    let mut stack: Vec<Vec<(GlobalType, Meaning)>> = Vec::new();
+    let mut result: Vec<&ReportDescriptorItem> = Vec::new();
+
+    for item in items {
+        match (&item.kind, meaning(item)) {
+            (ItemType::Global(GlobalType::Push), _) => stack.push(state.clone()),
+            (ItemType::Global(GlobalType::Pop), _) => state = stack.pop().unwrap_or(state),
+            (ItemType::Global(global_type), Some(meaning)) => {
+                match state.iter_mut().find(|(t, _)| t == global_type) {
+                    Some((_, current)) if *current == meaning => continue,
+                    Some((_, current)) => *current = meaning,
+                    None => state.push((global_type.clone(), meaning)),
+                }
+            }
+            _ => {}
+        }
+
+        result.push(item);
+    }
+
+    result
+}
+
+/// Shrink the payload to the smallest size with the same meaning
+fn shrink(item: &ReportDescriptorItem) -> ReportDescriptorItem {
+    let Some(expected) = meaning(item) else {
+        return item.clone();
+    };
+
+    let payload = item.raw_payload().to_vec();
+    for len in [0, 1, 2].into_iter().filter(|len| *len < payload.len()) {
+        let Ok(shrunk) = SizedPayload::try_from(&payload[..len]) else {
+            continue;
+        };
+
+        let Ok(candidate) = ReportDescriptorItem::try_from((item.kind.clone(), shrunk)) else {
+            continue;
+        };
+        if meaning(&candidate).as_ref() == Some(&expected) {
+            return candidate;
+        }
+    }
+
+    item.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report_builder::ReportDescriptorBuilder;
+    use crate::report_descriptor::parse::report_descriptor;
+
+    #[test]
+    fn shrink_respects_signedness() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .logical_minimum([0xff, 0xff, 0xff, 0xff])
+            .logical_maximum([0x7f, 0x00, 0x00, 0x00])
+            .physical_minimum([0x00, 0x00])
+            .physical_maximum([0xff, 0x00, 0x00, 0x00])
+            .build();
+
+        let minimised = descriptor.minimise().unwrap();
+
+        assert_eq!(
+            minimised.bytes(),
+            vec![0x15, 0xff, 0x25, 0x7f, 0x34, 0x46, 0xff, 0x00]
+        );
+    }
+
+    #[test]
+    fn extended_usage_is_kept() {
+        let descriptor = report_descriptor(&[
+            0x05, 0x01, 0x0b, 0x38, 0x02, 0x0c, 0x00, 0x75, 0x08, 0x95, 0x01, 0x81, 0x06,
+        ])
+        .unwrap();
+
+        assert_eq!(descriptor.minimise().unwrap(), descriptor);
+    }
+
+    #[test]
+    fn redundant_globals_after_pop() {
+        let descriptor = report_descriptor(&[
+            0x75, 0x08, 0x95, 0x01, 0xa4, 0x75, 0x10, 0x09, 0x30, 0x81, 0x02, 0xb4, 0x75, 0x08,
+            0x09, 0x31, 0x81, 0x02,
+        ])
+        .unwrap();
+
+        let minimised = descriptor.minimise().unwrap();
+
+        assert_eq!(
+            minimised.bytes(),
+            vec![
+                0x75, 0x08, 0x95, 0x01, 0xa4, 0x75, 0x10, 0x09, 0x30, 0x81, 0x02, 0xb4, 0x09, 0x31,
+                0x81, 0x02
+            ]
+        );
+    }
+
+    #[test]
+    fn data_field_options_keep_a_byte() {
+        let descriptor =
+            report_descriptor(&[0x75, 0x08, 0x95, 0x01, 0x82, 0x00, 0x00, 0x82, 0x00, 0x01])
+                .unwrap();
+
+        assert_eq!(
+            descriptor.minimise().unwrap().bytes(),
+            vec![0x75, 0x08, 0x95, 0x01, 0x81, 0x00, 0x82, 0x00, 0x01]
+        );
+    }
+}
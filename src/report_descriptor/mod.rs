@@ -1,13 +1,27 @@
 use crate::report_descriptor::data::*;
+use crate::report_descriptor::unit::{signed_nibble, Unit};
 use crate::usage_table::{Usage, UsagePage};
 use parse::data_field_options_from_payload;
+use std::ops::BitOr;
+
+pub use lint::{Lint, Rule, Severity};
+pub use tree::{ApplicationCollection, CollectionNode, CollectionTree, Collections, Node};
 
 /// Parsed descriptor report data
 pub mod data;
 
+mod lint;
+
+mod minimise;
+
 /// Parse descriptor report items
 pub mod parse;
 
+mod tree;
+
+/// Units of physical quantities
+pub mod unit;
+
 /// HID Descriptor Report item type
 #[derive(Debug, PartialEq, Clone)]
 pub enum ItemType {
@@ -19,6 +33,16 @@ pub enum ItemType {
 
     /// Local item type
     Local(LocalType),
+
+    /// Long item with its tag. No long item tags are defined by the specification,
+    /// so these are vendor specific.
+    ///
+    /// For definition in the HID protocol see
+    /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 27
+    Long(u8),
+
+    /// Short item of the reserved item type (0b11), with its tag
+    Reserved(u8),
 }
 
 /// Main item types
@@ -57,6 +81,9 @@ pub enum MainType {
     /// A terminating item used to specify the end of a
     /// collection of items.
     EndCollection,
+
+    /// Reserved Main item tag
+    Reserved(u8),
 }
 
 /// Local item
@@ -129,6 +156,9 @@ pub enum LocalType {
     /// Defines the beginning or end of a set of local items
     /// (1 = open set, 0 = close set).
     Delimiter,
+
+    /// Reserved Local item tag
+    Reserved(u8),
 }
 
 /// Global item
@@ -208,6 +238,9 @@ pub enum GlobalType {
     ///
     /// Replaces the item state table with the top structure from the stack.
     Pop,
+
+    /// Reserved Global item tag
+    Reserved(u8),
 }
 
 /// Data field options for Input, Output and Feature Items
@@ -353,13 +386,99 @@ impl From<(Mutability, Structure, Value)> for DataFieldOptions {
     }
 }
 
+impl DataFieldOptions {
+    /// Get the options as bits 0 to 8 of the item payload
+    fn bits(&self) -> u16 {
+        [
+            self.0 == Mutability::Constant,
+            self.1 == Structure::Variable,
+            self.2 == Value::Relative,
+            self.3 == Wrap::Wrap,
+            self.4 == Linear::NonLinear,
+            self.5 == State::NoPreferred,
+            self.6 == NullState::NullState,
+            self.7 == Volatile::Volatile,
+            self.8 == Data::BufferedBytes,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, set)| bits | (u16::from(*set) << i))
+    }
+
+    /// Get the options from bits 0 to 8 of the item payload
+    fn from_bits(bits: u16) -> Self {
+        DataFieldOptions(
+            Mutability::from_bit(bits & (1 << 0) != 0),
+            Structure::from_bit(bits & (1 << 1) != 0),
+            Value::from_bit(bits & (1 << 2) != 0),
+            Wrap::from_bit(bits & (1 << 3) != 0),
+            Linear::from_bit(bits & (1 << 4) != 0),
+            State::from_bit(bits & (1 << 5) != 0),
+            NullState::from_bit(bits & (1 << 6) != 0),
+            Volatile::from_bit(bits & (1 << 7) != 0),
+            Data::from_bit(bits & (1 << 8) != 0),
+        )
+    }
+}
+
+impl<T: Into<DataFieldOptions>> BitOr<T> for DataFieldOptions {
+    type Output = DataFieldOptions;
+
+    /// Combine options, the non-default value of each option wins
+    fn bitor(self, rhs: T) -> Self::Output {
+        DataFieldOptions::from_bits(self.bits() | rhs.into().bits())
+    }
+}
+
+/// Options can be combined into DataFieldOptions, `Data | Variable | Relative` for example
+macro_rules! data_field_option {
+    ($option:ident, $index:tt, $set:ident) => {
+        impl $option {
+            /// Get the option for its bit in the item payload
+            fn from_bit(set: bool) -> Self {
+                match set {
+                    true => $option::$set,
+                    false => $option::default(),
+                }
+            }
+        }
+
+        impl From<$option> for DataFieldOptions {
+            fn from(value: $option) -> Self {
+                let mut options = DataFieldOptions::default();
+                options.$index = value;
+                options
+            }
+        }
+
+        impl<T: Into<DataFieldOptions>> BitOr<T> for $option {
+            type Output = DataFieldOptions;
+
+            fn bitor(self, rhs: T) -> Self::Output {
+                DataFieldOptions::from(self) | rhs
+            }
+        }
+    };
+}
+
+data_field_option!(Mutability, 0, Constant);
+data_field_option!(Structure, 1, Variable);
+data_field_option!(Value, 2, Relative);
+data_field_option!(Wrap, 3, Wrap);
+data_field_option!(Linear, 4, NonLinear);
+data_field_option!(State, 5, NoPreferred);
+data_field_option!(NullState, 6, NullState);
+data_field_option!(Volatile, 7, Volatile);
+data_field_option!(Data, 8, BufferedBytes);
+
 /// Mutability indicates whether the item is data or a constant value
 ///
 /// For definition see <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 30
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Mutability {
     /// Data indicates the item is defining report
     /// fields that contain modifiable device data
+    #[default]
     Data,
 
     /// Indicates the item is a static read-only field in a
@@ -368,22 +487,17 @@ pub enum Mutability {
     Constant,
 }
 
-impl Default for Mutability {
-    fn default() -> Self {
-        Mutability::Data
-    }
-}
-
 /// Indicates whether the item creates variable or array
 /// data fields in reports.
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 30
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Structure {
     /// An array provides an alternate means for
     /// describing the data returned from a group of
     /// buttons.
+    #[default]
     Array,
 
     /// In variable fields, each field
@@ -391,151 +505,110 @@ pub enum Structure {
     Variable,
 }
 
-impl Default for Structure {
-    fn default() -> Self {
-        Structure::Array
-    }
-}
-
 /// Indicates whether the data is absolute (based on a fixed origin) or
 /// relative (indicating the change in value from the last report).
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 30
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Value {
     /// Absolute value based on a fixed origin
+    #[default]
     Absolute,
 
     /// Relative to the value from the last report
     Relative,
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Absolute
-    }
-}
-
 /// Indicates whether the data “rolls over” when
 /// reaching either the extreme high or low value.
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 31
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Wrap {
     /// No wrapping of the value
+    #[default]
     NoWrap,
 
     /// Wrap the value
     Wrap,
 }
 
-impl Default for Wrap {
-    fn default() -> Self {
-        Wrap::NoWrap
-    }
-}
-
 /// Indicates whether the raw data from the device has been processed in some way, and no longer
 /// represents a linear relationship between what is measured and the data that is reported.
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 31
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Linear {
     /// Linear data
+    #[default]
     Linear,
 
     /// Non linear data
     NonLinear,
 }
 
-impl Default for Linear {
-    fn default() -> Self {
-        Linear::Linear
-    }
-}
-
 /// Indicates whether the control has a preferred state to which it will return
 /// when the user is not physically interacting with the control.
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 31
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum State {
     /// Control has a preferred state
+    #[default]
     Preferred,
 
     /// Control doesn't have a preferred state (push buttons for example)
     NoPreferred,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        State::Preferred
-    }
-}
-
 /// Indicates whether the control has a state in which it
 /// is not sending meaningful data.
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 31
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum NullState {
     /// Control doesn't have a null state
+    #[default]
     NoNullPosition,
 
     /// Control has a null state
     NullState,
 }
 
-impl Default for NullState {
-    fn default() -> Self {
-        NullState::NoNullPosition
-    }
-}
-
 /// Indicates whether the Feature or Output control's
 /// value should be changed by the host or not.
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 31
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Volatile {
     /// Non volatile data
+    #[default]
     NonVolatile,
 
     /// Volatile output can change with or without host interaction.
     Volatile,
 }
 
-impl Default for Volatile {
-    fn default() -> Self {
-        Volatile::NonVolatile
-    }
-}
-
 /// Indicates that the control emits a fixed-size stream of bytes.
 ///
 /// For definition in the HID protocol see
 /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 31
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum Data {
     /// Fixed size data
+    #[default]
     BitField,
 
     /// Bytes
     BufferedBytes,
 }
 
-impl Default for Data {
-    fn default() -> Self {
-        Data::BitField
-    }
-}
-
 /// The collection main item
 ///
 /// For definition in the HID protocol see
@@ -613,7 +686,30 @@ pub struct ReportDescriptorItem {
 impl ReportDescriptorItem {
     /// Get the raw payload of the Report Descriptor Item
     pub fn raw_payload(&self) -> SizedPayload {
-        SizedPayload::try_from(&self.raw[1..]).unwrap()
+        if let ItemType::Long(_) = self.kind {
+            return SizedPayload::Long(self.raw.get(3..).unwrap_or_default().to_vec());
+        }
+
+        self.raw
+            .get(1..)
+            .and_then(|payload| SizedPayload::try_from(payload).ok())
+            .unwrap_or_default()
+    }
+
+    /// Determine if current item has a reserved tag
+    pub fn is_reserved(&self) -> bool {
+        matches!(
+            self.kind,
+            ItemType::Main(MainType::Reserved(_))
+                | ItemType::Global(GlobalType::Reserved(_))
+                | ItemType::Local(LocalType::Reserved(_))
+                | ItemType::Reserved(_)
+        )
+    }
+
+    /// Determine if current item is a long item
+    pub fn is_long(&self) -> bool {
+        matches!(self.kind, ItemType::Long(_))
     }
 
     /// Get the payload as u8 value
@@ -656,6 +752,41 @@ impl ReportDescriptorItem {
         self.kind == ItemType::Local(LocalType::UsageMaximum)
     }
 
+    /// Determine if current item describes the Designator Index
+    pub fn is_designator_index(&self) -> bool {
+        self.kind == ItemType::Local(LocalType::DesignatorIndex)
+    }
+
+    /// Determine if current item describes the Designator Minimum
+    pub fn is_designator_minimum(&self) -> bool {
+        self.kind == ItemType::Local(LocalType::DesignatorMinimum)
+    }
+
+    /// Determine if current item describes the Designator Maximum
+    pub fn is_designator_maximum(&self) -> bool {
+        self.kind == ItemType::Local(LocalType::DesignatorMaximum)
+    }
+
+    /// Determine if current item describes the String Index
+    pub fn is_string_index(&self) -> bool {
+        self.kind == ItemType::Local(LocalType::StringIndex)
+    }
+
+    /// Determine if current item describes the String Minimum
+    pub fn is_string_minimum(&self) -> bool {
+        self.kind == ItemType::Local(LocalType::StringMinimum)
+    }
+
+    /// Determine if current item describes the String Maximum
+    pub fn is_string_maximum(&self) -> bool {
+        self.kind == ItemType::Local(LocalType::StringMaximum)
+    }
+
+    /// Determine if current item describes a Delimiter
+    pub fn is_delimiter(&self) -> bool {
+        self.kind == ItemType::Local(LocalType::Delimiter)
+    }
+
     /// Determine if current item describes the Logical Minimum
     pub fn is_logical_minimum(&self) -> bool {
         self.kind == ItemType::Global(GlobalType::LogicalMinimum)
@@ -666,6 +797,26 @@ impl ReportDescriptorItem {
         self.kind == ItemType::Global(GlobalType::LogicalMaximum)
     }
 
+    /// Determine if current item describes the Physical Minimum
+    pub fn is_physical_minimum(&self) -> bool {
+        self.kind == ItemType::Global(GlobalType::PhysicalMinimum)
+    }
+
+    /// Determine if current item describes the Physical Maximum
+    pub fn is_physical_maximum(&self) -> bool {
+        self.kind == ItemType::Global(GlobalType::PhysicalMaximum)
+    }
+
+    /// Determine if current item describes the Unit Exponent
+    pub fn is_unit_exponent(&self) -> bool {
+        self.kind == ItemType::Global(GlobalType::UnitExponent)
+    }
+
+    /// Determine if current item describes the Unit
+    pub fn is_unit(&self) -> bool {
+        self.kind == ItemType::Global(GlobalType::Unit)
+    }
+
     /// Determine if current item describes the Report Size
     pub fn is_report_size(&self) -> bool {
         self.kind == ItemType::Global(GlobalType::ReportSize)
@@ -681,6 +832,16 @@ impl ReportDescriptorItem {
         self.kind == ItemType::Global(GlobalType::ReportID)
     }
 
+    /// Determine if current item is a Push of the global item state
+    pub fn is_push(&self) -> bool {
+        self.kind == ItemType::Global(GlobalType::Push)
+    }
+
+    /// Determine if current item is a Pop of the global item state
+    pub fn is_pop(&self) -> bool {
+        self.kind == ItemType::Global(GlobalType::Pop)
+    }
+
     /// Determine if current item describes Input
     pub fn is_input(&self) -> bool {
         self.kind == ItemType::Main(MainType::Input)
@@ -721,16 +882,70 @@ impl ReportDescriptorItem {
         Some(UsagePage::from(self.payload_u16()?))
     }
 
+    /// Determine if current item is a Usage, Usage Minimum or Usage Maximum
+    /// with a four byte payload. The high word of an extended usage is the
+    /// usage page, which overrides the global Usage Page.
+    ///
+    /// For definition in the HID protocol see
+    /// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 40
+    pub fn is_extended_usage(&self) -> bool {
+        (self.is_usage() || self.is_usage_minimum() || self.is_usage_maximum())
+            && self.payload_size == Size::Four
+    }
+
+    /// Get the Usage Page and Usage ID of a Usage, Usage Minimum or Usage Maximum
+    /// item. The given usage page is used unless the item is an extended usage.
+    /// This function will return None if the item does not describe a usage or
+    /// if the payload couldn't be converted.
+    pub fn usage_page_and_id(&self, usage_page: &UsagePage) -> Option<(UsagePage, u16)> {
+        if self.is_extended_usage() {
+            let value = self.payload_u32();
+            return Some((UsagePage::from((value >> 16) as u16), value as u16));
+        }
+
+        if !(self.is_usage() || self.is_usage_minimum() || self.is_usage_maximum()) {
+            return None;
+        }
+
+        Some((usage_page.clone(), self.payload_u16()?))
+    }
+
     /// Get the Usage given a UsagePage. This function will return None
     /// if the current item does not describe a Usage or if the payload
-    /// couldn't be converted.
+    /// couldn't be converted. An extended usage is resolved against its
+    /// own usage page.
     pub fn usage(&self, usage_page: &UsagePage) -> Option<Usage> {
         if !self.is_usage() {
             return None;
         }
 
-        let id = self.payload_u16()?;
-        Some(Usage::from((usage_page, id)))
+        let (usage_page, id) = self.usage_page_and_id(usage_page)?;
+        Some(Usage::from((&usage_page, id)))
+    }
+
+    /// Get the Unit. Will return None if this item doesn't describe the Unit.
+    pub fn unit(&self) -> Option<Unit> {
+        if !self.is_unit() {
+            return None;
+        }
+
+        Some(Unit::from(self.payload_u32()))
+    }
+
+    /// Get the Unit Exponent (base 10). Will return None if this item doesn't
+    /// describe the Unit Exponent.
+    ///
+    /// The specification stores the exponent as a signed nibble, but many devices
+    /// use a full signed byte instead (e.g. `0xfd` for -3). Both are accepted.
+    pub fn unit_exponent(&self) -> Option<i32> {
+        if !self.is_unit_exponent() {
+            return None;
+        }
+
+        match self.payload_u32() {
+            i if i <= 0xf => Some(i32::from(signed_nibble(i as u8))),
+            _ => Some(self.payload_i32()),
+        }
     }
 
     /// Get the Collection type. If this item doesn't describe the Collection
@@ -756,23 +971,68 @@ impl ReportDescriptorItem {
             return None;
         }
 
-        data_field_options_from_payload(payload.data().unwrap(), payload.size()).ok()
+        data_field_options_from_payload(payload.data()?, payload.size()?)
     }
 }
 
-impl<T: Into<ItemType>, U: Into<SizedPayload>> From<(T, U)> for ReportDescriptorItem {
-    fn from(value: (T, U)) -> Self {
+/// Reasons a [`ReportDescriptorItem`] can not be encoded
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum ItemError {
+    /// The data of a long item is longer than 255 bytes
+    #[error("long item data of {0} bytes is longer than 255 bytes")]
+    LongDataTooLarge(usize),
+
+    /// A long payload was given for an item that is not a long item
+    #[error("only long items can have a long payload")]
+    LongPayload,
+
+    /// A reserved item type uses a tag of a defined item, or collides with the long
+    /// item prefix (`ItemType::Reserved(0xf)` with a two byte payload)
+    #[error("{kind} with payload size {size:?} does not have a reserved tag")]
+    TagNotReserved {
+        /// The item type
+        kind: ItemType,
+
+        /// The payload size
+        size: Size,
+    },
+}
+
+impl<T: Into<ItemType>, U: Into<SizedPayload>> TryFrom<(T, U)> for ReportDescriptorItem {
+    type Error = ItemError;
+
+    fn try_from(value: (T, U)) -> Result<Self, Self::Error> {
         let kind: ItemType = value.0.into();
         let payload: SizedPayload = value.1.into();
 
-        let size = payload.size();
-        let prefix = PrefixByte::from((&kind, &size));
+        // A long item has its data size and tag between the prefix and the data
+        if let ItemType::Long(tag) = kind {
+            let data = payload.to_vec();
+            let data_size =
+                u8::try_from(data.len()).map_err(|_| ItemError::LongDataTooLarge(data.len()))?;
+
+            let size = Size::Long(data_size);
+            let prefix = PrefixByte::try_from((&kind, &size))?;
+            let header = vec![data_size, tag];
+
+            return Ok(ReportDescriptorItem {
+                kind,
+                payload_size: size,
+                raw: prefix.into_vec_append([header, data].concat()),
+            });
+        }
 
-        ReportDescriptorItem {
+        let size = match payload.size() {
+            Some(Size::Long(_)) | None => return Err(ItemError::LongPayload),
+            Some(size) => size,
+        };
+        let prefix = PrefixByte::try_from((&kind, &size))?;
+
+        Ok(ReportDescriptorItem {
             kind,
             payload_size: size,
             raw: prefix.into_vec_append(payload.to_vec()),
-        }
+        })
     }
 }
 
@@ -812,7 +1072,8 @@ impl ReportDescriptor {
 
 #[cfg(test)]
 mod tests {
-    use crate::report_descriptor::Collection;
+    use crate::report_descriptor::parse::report_descriptor;
+    use crate::report_descriptor::*;
 
     #[test]
     fn collection_from_u8_reserved() {
@@ -823,4 +1084,28 @@ mod tests {
     fn collection_from_u8_vendor_defined() {
         assert_eq!(Collection::from(0xF0), Collection::VendorDefined(0xF0))
     }
+
+    #[test]
+    fn data_field_options_combine() {
+        let options = Mutability::Data | Structure::Variable | Value::Relative;
+        assert_eq!(
+            options,
+            DataFieldOptions::from((Mutability::Data, Structure::Variable, Value::Relative))
+        );
+        assert_eq!(SizedPayload::from(options), SizedPayload::One([0x06]));
+    }
+
+    #[test]
+    fn data_field_options_round_trip() {
+        let options = Mutability::Constant
+            | Wrap::Wrap
+            | State::NoPreferred
+            | Volatile::Volatile
+            | Data::BufferedBytes;
+        let payload = SizedPayload::from(options.clone());
+        assert_eq!(payload, SizedPayload::Two([0xa9, 0x01]));
+
+        let descriptor = report_descriptor(&[0x82, 0xa9, 0x01]).unwrap();
+        assert_eq!(descriptor.items()[0].data_field_options(), Some(options));
+    }
 }
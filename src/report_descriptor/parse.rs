@@ -1,5 +1,4 @@
-use crate::error::Error;
-use crate::report_descriptor::data::Size;
+use crate::report_descriptor::data::{Size, LONG_ITEM_PREFIX};
 use crate::report_descriptor::{
     Data, DataFieldOptions, GlobalType, ItemType, Linear, LocalType, MainType, Mutability,
     NullState, ReportDescriptor, ReportDescriptorItem, State, Structure, Value, Volatile, Wrap,
@@ -7,11 +6,10 @@ use crate::report_descriptor::{
 use nom::bits::complete::take as take_bits;
 use nom::bytes::complete::take;
 use nom::combinator::{all_consuming, map, map_res};
-use nom::error::{
-    context, ContextError, FromExternalError, ParseError, VerboseError, VerboseErrorKind,
-};
+use nom::error::{context, ContextError, FromExternalError, ParseError};
 use nom::multi::many0;
 use nom::{Finish, IResult, ToUsize};
+use thiserror::Error as ThisError;
 
 /// Parse the payload size from the first byte
 fn size<'a, E>(input: &'a [u8]) -> IResult<&'a [u8], Size, E>
@@ -73,22 +71,13 @@ where
             0b10001000 => Ok(ItemType::Local(LocalType::StringMinimum)),
             0b10011000 => Ok(ItemType::Local(LocalType::StringMaximum)),
             0b10101000 => Ok(ItemType::Local(LocalType::Delimiter)),
-            i => Err(MapResultError::PrefixTypeInvalid(i)),
+
+            // Tags reserved for future use are kept, so they survive a round-trip
+            prefix => Ok(reserved_item_type(prefix)),
         }),
     )(input)
 }
 
-/// Parse a long item
-fn long_item_size_and_type<'a, E>(_input: &'a [u8]) -> IResult<&'a [u8], (Size, ItemType), E>
-where
-    E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + FromExternalError<&'a [u8], MapResultError>,
-{
-    unimplemented!();
-    // From specs: "No long item tags are defined in this document. These
-    // tags are reserved for future use."
-    // Page 27 (https://www.usb.org/sites/default/files/hid1_11.pdf)
-}
-
 /// Parse size and byte from first byte
 fn size_and_type<'a, E>(input: &'a [u8]) -> IResult<&'a [u8], (Size, ItemType), E>
 where
@@ -96,11 +85,6 @@ where
 {
     let (input, prefix) = context("take prefix byte", take(1usize))(input)?;
 
-    // From long item
-    if prefix[0] == 0b11111110 {
-        return long_item_size_and_type(input);
-    }
-
     // Both item type and size are in the first bye
     let (_, hid) = item_type(prefix)?;
     let (_, size) = size(prefix)?;
@@ -113,6 +97,11 @@ fn descriptor_item<'a, E>(input: &'a [u8]) -> IResult<&'a [u8], ReportDescriptor
 where
     E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + FromExternalError<&'a [u8], MapResultError>,
 {
+    // Long items have their size and type in the next two bytes
+    if input.first() == Some(&LONG_ITEM_PREFIX) {
+        return long_item(input);
+    }
+
     // Get size and type from first byte
     let (input, prefix) = context("take prefix byte", take(1usize))(input)?;
     let (_, (size, hid)) = size_and_type(prefix)?;
@@ -135,6 +124,29 @@ where
     ))
 }
 
+/// Parse a long item: the prefix byte, the data size, the tag and the data bytes
+///
+/// From specs: "No long item tags are defined in this document. These tags are
+/// reserved for future use." Page 27 (<https://www.usb.org/sites/default/files/hid1_11.pdf>)
+fn long_item<'a, E>(input: &'a [u8]) -> IResult<&'a [u8], ReportDescriptorItem, E>
+where
+    E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + FromExternalError<&'a [u8], MapResultError>,
+{
+    let (input, header) = context("take long item header", take(3usize))(input)?;
+    let (data_size, tag) = (header[1], header[2]);
+
+    let (input, data) = context("take long item data", take(data_size))(input)?;
+
+    Ok((
+        input,
+        ReportDescriptorItem {
+            kind: ItemType::Long(tag),
+            payload_size: Size::Long(data_size),
+            raw: [header, data].concat(),
+        },
+    ))
+}
+
 /// Parse all bytes from input into a ReportDescriptorItemList
 fn full_report_descriptor<'a, E>(input: &'a [u8]) -> IResult<&'a [u8], ReportDescriptor, E>
 where
@@ -150,6 +162,9 @@ where
 
 /// Parse raw bytes to ReportDescriptor
 ///
+/// Parsing never panics: bytes that can not be parsed result in a [`DescriptorError`]
+/// pointing to the offending item.
+///
 /// # Example
 ///
 /// ```
@@ -165,22 +180,167 @@ where
 /// let parsed = parse::report_descriptor(&bytes).unwrap();
 /// println!("{}", parsed);
 /// ```
-pub fn report_descriptor(input: &[u8]) -> Result<ReportDescriptor, Error> {
-    // Parse report descriptor or result in a VerboseError
-    let result = full_report_descriptor::<VerboseError<&[u8]>>(input);
-
-    result
+pub fn report_descriptor(input: &[u8]) -> Result<ReportDescriptor, DescriptorError> {
+    full_report_descriptor::<nom::error::Error<&[u8]>>(input)
         .finish()
-        .map(|r| {
-            assert!(r.0.is_empty()); // Verify that all the bytes have been successfully parsed
-            r.1
-        })
-        .map_err(|e| Error::ParsingFailed(user_friendly_error(e)))
+        .map(|(_, descriptor)| descriptor)
+        .map_err(|e| DescriptorError::at(input, input.len() - e.input.len()))
+}
+
+/// Parse raw bytes to ReportDescriptor, recovering from errors where possible
+///
+/// Like the Linux kernel, this tolerates descriptors that [`report_descriptor`] rejects:
+/// a truncated final item ends the descriptor. Items with a reserved tag are kept
+/// as they are in both modes, but only reported here. Every recovery is returned as
+/// a [`Diagnostic`].
+///
+/// # Example
+///
+/// ```
+/// use hid_tools::report_descriptor::parse::report_descriptor_lenient;
+///
+/// // Usage Page (Generic Desktop), followed by a truncated Usage item
+/// let (descriptor, diagnostics) = report_descriptor_lenient(&[0x05, 0x01, 0x0a, 0x30]);
+///
+/// assert_eq!(descriptor.items().len(), 1);
+/// assert_eq!(diagnostics[0].offset(), 2);
+/// ```
+pub fn report_descriptor_lenient(input: &[u8]) -> (ReportDescriptor, Vec<Diagnostic>) {
+    let mut items = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut offset = 0;
+
+    while offset < input.len() {
+        let item = match descriptor_item::<nom::error::Error<&[u8]>>(&input[offset..]) {
+            Ok((_, item)) => item,
+            Err(_) => {
+                diagnostics.push(Diagnostic::Truncated(DescriptorError::at(input, offset)));
+                break;
+            }
+        };
+
+        if item.is_reserved() {
+            diagnostics.push(Diagnostic::ReservedTag {
+                offset,
+                prefix: item.raw[0],
+            });
+        }
+
+        offset += item.raw.len();
+        items.push(item);
+    }
+
+    (ReportDescriptor::new(items), diagnostics)
+}
+
+/// The reserved item type for a prefix byte, based on the item type bits
+fn reserved_item_type(prefix: u8) -> ItemType {
+    let tag = prefix >> 4;
+
+    match (prefix >> 2) & 0x3 {
+        0 => ItemType::Main(MainType::Reserved(tag)),
+        1 => ItemType::Global(GlobalType::Reserved(tag)),
+        2 => ItemType::Local(LocalType::Reserved(tag)),
+        _ => ItemType::Reserved(tag),
+    }
+}
+
+/// Error while parsing the bytes of a Report Descriptor
+#[derive(Debug, PartialEq, Eq, Clone, ThisError)]
+pub enum DescriptorError {
+    /// The descriptor ends before the item is complete
+    #[error(
+        "item {prefix:#04x} at offset {offset} needs {expected} bytes after the prefix, \
+         but only {available} are available"
+    )]
+    Truncated {
+        /// Offset of the prefix byte in the descriptor
+        offset: usize,
+
+        /// The prefix byte
+        prefix: u8,
+
+        /// Number of bytes the item needs after its prefix byte
+        expected: usize,
+
+        /// Number of bytes left in the descriptor after the prefix byte
+        available: usize,
+    },
+}
+
+impl DescriptorError {
+    /// Describe the item at `offset` that could not be parsed. Every prefix byte is a
+    /// valid item, so the only reason is that the item is truncated.
+    fn at(input: &[u8], offset: usize) -> Self {
+        let prefix = input.get(offset).copied().unwrap_or_default();
+        let available = input.len().saturating_sub(offset + 1);
+
+        let expected = if prefix == LONG_ITEM_PREFIX {
+            // Data size and tag, followed by the data
+            2 + input.get(offset + 1).map_or(0, |size| *size as usize)
+        } else {
+            size::<nom::error::Error<&[u8]>>(&[prefix]).map_or(0, |(_, size)| (&size).to_usize())
+        };
+
+        DescriptorError::Truncated {
+            offset,
+            prefix,
+            expected,
+            available,
+        }
+    }
+
+    /// Get the offset of the offending item in the descriptor
+    pub fn offset(&self) -> usize {
+        match self {
+            DescriptorError::Truncated { offset, .. } => *offset,
+        }
+    }
+
+    /// Get the prefix byte of the offending item
+    pub fn prefix(&self) -> u8 {
+        match self {
+            DescriptorError::Truncated { prefix, .. } => *prefix,
+        }
+    }
+}
+
+/// Something [`report_descriptor_lenient`] recovered from
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Diagnostic {
+    /// The prefix byte has a tag that is reserved for future use. The item is kept.
+    ReservedTag {
+        /// Offset of the prefix byte in the descriptor
+        offset: usize,
+
+        /// The prefix byte
+        prefix: u8,
+    },
+
+    /// The final item is truncated and ends the descriptor
+    Truncated(DescriptorError),
+}
+
+impl Diagnostic {
+    /// Get the offset of the offending item in the descriptor
+    pub fn offset(&self) -> usize {
+        match self {
+            Diagnostic::ReservedTag { offset, .. } => *offset,
+            Diagnostic::Truncated(error) => error.offset(),
+        }
+    }
+
+    /// Get the prefix byte of the offending item
+    pub fn prefix(&self) -> u8 {
+        match self {
+            Diagnostic::ReservedTag { prefix, .. } => *prefix,
+            Diagnostic::Truncated(error) => error.prefix(),
+        }
+    }
 }
 
 /// Errors for converting size and Hid Item Type
 enum MapResultError {
-    PrefixTypeInvalid(u8),
     Impossible,
 }
 
@@ -192,28 +352,11 @@ impl ToUsize for &Size {
             Size::One => 1,
             Size::Two => 2,
             Size::Four => 4,
+            Size::Long(size) => *size as usize,
         }
     }
 }
 
-/// Attempt to create an error message that is better understandable by humans
-fn user_friendly_error(error: VerboseError<&[u8]>) -> String {
-    let mut message = Vec::new();
-
-    for e in error.errors.into_iter() {
-        match &e.1 {
-            VerboseErrorKind::Context(context) => {
-                message.push(format!("For input {:04x?} could not {} \n\n", e.0, context))
-            }
-            //_ => {}
-            VerboseErrorKind::Char(c) => message.push(format!("'{}':\t{:?}\n", c, e.0)),
-            VerboseErrorKind::Nom(kind) => message.push(format!("{:?}:\t{:?}\n", kind, e.0)),
-        };
-    }
-
-    message.into_iter().collect()
-}
-
 /// Parse Mutability (Data/Constant) from bit input
 fn mutability(input: (&[u8], usize)) -> IResult<(&[u8], usize), Mutability> {
     map(take_bits(1usize), |bit: u8| {
@@ -318,27 +461,25 @@ fn data_field_options(
     input: (&[u8], usize),
     bytes_to_parse: Size,
 ) -> IResult<(&[u8], usize), DataFieldOptions> {
-    let (input, data) = match bytes_to_parse {
-        Size::Four => {
-            let (input, _): ((&[u8], usize), u8) = take_bits(8 + 8 + 7usize)(input)?; // skip first 23 bits
-            data(input)?
-        }
-        Size::Two => {
-            let (input, _): ((&[u8], usize), u8) = take_bits(7usize)(input)?; // skip first 7 bits
-            data(input)?
-        }
-        _ => (input, Data::default()),
-    };
-
+    // Bits 0 to 7 are in the first byte, most significant bit first
     let (input, volatile) = volatile(input)?;
     let (input, null_state) = null_state(input)?;
     let (input, state) = state(input)?;
-    let (input, wrap) = wrap(input)?;
     let (input, linear) = linear(input)?;
+    let (input, wrap) = wrap(input)?;
     let (input, val) = value(input)?;
     let (input, structure) = structure(input)?;
     let (input, mutability) = mutability(input)?;
 
+    // Bit 8 (Buffered Bytes) is the least significant bit of the second byte
+    let (input, data) = match bytes_to_parse {
+        Size::Two | Size::Four => {
+            let (input, _): ((&[u8], usize), u8) = take_bits(7usize)(input)?; // skip bits 15 to 9
+            data(input)?
+        }
+        _ => (input, Data::default()),
+    };
+
     Ok((
         input,
         DataFieldOptions::from((
@@ -351,24 +492,22 @@ fn data_field_options(
 pub(super) fn data_field_options_from_payload(
     payload: &[u8],
     bytes_to_parse: Size,
-) -> Result<DataFieldOptions, Error> {
+) -> Option<DataFieldOptions> {
     if payload.is_empty() {
-        return Err(Error::PayloadEmpty);
+        return None;
     }
 
     data_field_options((payload, 0), bytes_to_parse)
         .finish()
         .map(|v| v.1)
-        .map_err(|_| {
-            Error::ParsingFailed("Could not parse payload to data field options".to_string())
-        })
+        .ok()
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::error::Error::ParsingFailed;
+    use crate::report_descriptor::data::PrefixByte;
     use crate::report_descriptor::GlobalType;
 
     type SimpleError<'a> = nom::error::Error<&'a [u8]>;
@@ -434,11 +573,59 @@ mod tests {
     }
 
     #[test]
-    fn item_type_failure() {
+    fn item_type_reserved() {
         let bytes: Vec<u8> = vec![0xff];
         let result = item_type::<SimpleError>(&bytes);
 
-        assert!(result.is_err())
+        assert_eq!(result, Ok((&bytes[1..], ItemType::Reserved(0xf))))
+    }
+
+    #[test]
+    fn item_type_reserved_per_class() {
+        let cases = [
+            (0xd0, ItemType::Main(MainType::Reserved(0xd))),
+            (0xc4, ItemType::Global(GlobalType::Reserved(0xc))),
+            (0x68, ItemType::Local(LocalType::Reserved(0x6))),
+            (0x1c, ItemType::Reserved(0x1)),
+        ];
+
+        for (prefix, kind) in cases {
+            assert_eq!(item_type::<SimpleError>(&[prefix]), Ok((&[][..], kind)));
+        }
+    }
+
+    #[test]
+    fn every_short_item_prefix_round_trips() {
+        for prefix in (0..=u8::MAX).filter(|prefix| *prefix != LONG_ITEM_PREFIX) {
+            let (_, (size, kind)) = size_and_type::<SimpleError>(&[prefix]).unwrap();
+            assert_eq!(PrefixByte::try_from((&kind, &size)).unwrap().u8(), prefix);
+        }
+    }
+
+    #[test]
+    fn reserved_items_round_trip() {
+        let bytes: Vec<u8> = vec![0x05, 0x01, 0xd1, 0x01, 0xc6, 0x01, 0x02, 0x68, 0x1d, 0x01];
+        let descriptor = report_descriptor(&bytes).unwrap();
+
+        assert_eq!(descriptor.items().len(), 5);
+        assert!(descriptor.items()[1..]
+            .iter()
+            .all(|item| item.is_reserved()));
+        assert_eq!(
+            ReportDescriptor::new(
+                descriptor
+                    .items()
+                    .iter()
+                    .map(|item| {
+                        ReportDescriptorItem::try_from((item.kind.clone(), item.raw_payload()))
+                            .unwrap()
+                    })
+                    .collect()
+            )
+            .bytes(),
+            bytes
+        );
+        assert_eq!(descriptor.bytes(), bytes);
     }
 
     #[test]
@@ -450,14 +637,149 @@ mod tests {
     }
 
     #[test]
-    fn report_error_string() {
+    fn report_error_reserved_prefix_truncated() {
         let bytes: Vec<u8> = vec![0x06, 0xd0, 0xf1, 0xff, 0x09, 0x01];
         let result = report_descriptor(&bytes).expect_err("bytes is not valid");
 
         assert_eq!(
             result,
-            ParsingFailed("Eof:\t[255, 9, 1]\nFor input [0006, 00d0, 00f1, 00ff, 0009, 0001] could not parse all bytes to report descriptor \n\n".to_string())
-        )
+            DescriptorError::Truncated {
+                offset: 3,
+                prefix: 0xff,
+                expected: 4,
+                available: 2
+            }
+        );
+        assert_eq!(
+            result.to_string(),
+            "item 0xff at offset 3 needs 4 bytes after the prefix, but only 2 are available"
+        );
+    }
+
+    #[test]
+    fn report_error_truncated_payload() {
+        let bytes: Vec<u8> = vec![0x05, 0x01, 0x27, 0xff, 0xff];
+        let result = report_descriptor(&bytes).expect_err("bytes is not valid");
+
+        assert_eq!(
+            result,
+            DescriptorError::Truncated {
+                offset: 2,
+                prefix: 0x27,
+                expected: 4,
+                available: 2
+            }
+        );
+    }
+
+    #[test]
+    fn arbitrary_input_does_not_panic() {
+        // Every prefix byte followed by every possible number of payload bytes
+        for prefix in 0..=u8::MAX {
+            for len in 0..6 {
+                for fill in [0x00, 0xfe, 0xff] {
+                    let mut bytes = vec![prefix];
+                    bytes.resize(1 + len, fill);
+
+                    let (lenient, _) = report_descriptor_lenient(&bytes);
+                    assert!(bytes.starts_with(&lenient.bytes()));
+
+                    if let Ok(descriptor) = report_descriptor(&bytes) {
+                        let _ = descriptor.to_string();
+                        assert_eq!(descriptor.bytes(), bytes);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_item_round_trip() {
+        let bytes: Vec<u8> = vec![0x05, 0x01, 0xfe, 0x03, 0x42, 0xaa, 0xbb, 0xcc, 0x09, 0x02];
+        let result = report_descriptor(&bytes).unwrap();
+
+        assert_eq!(
+            result.items()[1],
+            ReportDescriptorItem {
+                kind: ItemType::Long(0x42),
+                payload_size: Size::Long(3),
+                raw: vec![0xfe, 0x03, 0x42, 0xaa, 0xbb, 0xcc],
+            }
+        );
+        assert_eq!(result.bytes(), bytes);
+    }
+
+    #[test]
+    fn long_item_without_data() {
+        let bytes: Vec<u8> = vec![0xfe, 0x00, 0x01];
+        let result = report_descriptor(&bytes).unwrap();
+
+        assert_eq!(result.items()[0].kind, ItemType::Long(0x01));
+        assert!(result.items()[0].raw_payload().is_empty());
+    }
+
+    #[test]
+    fn truncated_long_item_is_err() {
+        assert!(report_descriptor(&[0xfe]).is_err());
+        assert_eq!(
+            report_descriptor(&[0x09, 0x01, 0xfe, 0x04, 0x01, 0xaa]),
+            Err(DescriptorError::Truncated {
+                offset: 2,
+                prefix: 0xfe,
+                expected: 6,
+                available: 3
+            })
+        );
+    }
+
+    #[test]
+    fn lenient_keeps_unknown_tags() {
+        // Usage Page, reserved Global item 0xd with one byte payload, Usage
+        let bytes: Vec<u8> = vec![0x05, 0x01, 0xd5, 0x42, 0x09, 0x02];
+        let (descriptor, diagnostics) = report_descriptor_lenient(&bytes);
+
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::ReservedTag {
+                offset: 2,
+                prefix: 0xd5
+            }]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "reserved item tag 0xd5 at offset 2"
+        );
+        assert_eq!(
+            descriptor.items()[1].kind,
+            ItemType::Global(GlobalType::Reserved(0xd))
+        );
+        assert_eq!(descriptor.bytes(), bytes);
+    }
+
+    #[test]
+    fn lenient_drops_truncated_tail() {
+        let bytes: Vec<u8> = vec![0x05, 0x01, 0x09, 0x02, 0xa1, 0x01, 0x75];
+        let (descriptor, diagnostics) = report_descriptor_lenient(&bytes);
+
+        assert_eq!(descriptor.items().len(), 3);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::Truncated(DescriptorError::Truncated {
+                offset: 6,
+                prefix: 0x75,
+                expected: 1,
+                available: 0
+            })]
+        );
+    }
+
+    #[test]
+    fn lenient_equals_strict_for_valid_input() {
+        let bytes: Vec<u8> = vec![0x05, 0x01, 0x09, 0x02, 0xa1, 0x01, 0xc0];
+        let (descriptor, diagnostics) = report_descriptor_lenient(&bytes);
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(Ok(descriptor), report_descriptor(&bytes));
     }
 
     #[test]
@@ -483,13 +805,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn data_field_options_buffered_bytes() {
+        let bytes: Vec<u8> = vec![0b0000_0010, 0b0000_0001, 0x00, 0x00];
+        let result = data_field_options_from_payload(&bytes, Size::Four).unwrap();
+
+        assert_eq!(result.structure(), &Structure::Variable);
+        assert_eq!(result.data(), &Data::BufferedBytes);
+    }
+
+    #[test]
+    fn data_field_options_wrap_and_linear() {
+        let wrap = data_field_options_from_payload(&[0b0000_1000], Size::One).unwrap();
+        assert_eq!(wrap.wrap(), &Wrap::Wrap);
+        assert_eq!(wrap.linear(), &Linear::Linear);
+
+        let non_linear = data_field_options_from_payload(&[0b0001_0000], Size::One).unwrap();
+        assert_eq!(non_linear.wrap(), &Wrap::NoWrap);
+        assert_eq!(non_linear.linear(), &Linear::NonLinear);
+    }
+
     #[test]
     fn data_field_options_2() {
         let bytes: Vec<u8> = vec![0b1111_1111];
         let result = data_field_options_from_payload(&bytes, Size::One);
         assert_eq!(
             result,
-            Ok(DataFieldOptions::from((
+            Some(DataFieldOptions::from((
                 Mutability::Constant,
                 Structure::Variable,
                 Value::Relative,
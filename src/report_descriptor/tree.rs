@@ -0,0 +1,555 @@
+use crate::report::input::GlobalItemTracker;
+use crate::report_descriptor::{
+    Collection, ItemType, LocalType, MainType, ReportDescriptor, ReportDescriptorItem,
+};
+use crate::usage_table::{Usage, UsagePage};
+use std::collections::BTreeSet;
+
+/// The collections of a Report Descriptor as a tree
+///
+/// The roots are the top-level collections (and main items outside of any
+/// collection, which are rare).
+#[derive(Debug, PartialEq)]
+pub struct CollectionTree<'a> {
+    children: Vec<Node<'a>>,
+}
+
+/// A collection with its usage, nested collections and main items
+#[derive(Debug, PartialEq)]
+pub struct CollectionNode<'a> {
+    item: &'a ReportDescriptorItem,
+    collection: Collection,
+    usage_page: UsagePage,
+    usage: Usage,
+    children: Vec<Node<'a>>,
+}
+
+/// A child in the collection tree
+#[derive(Debug, PartialEq)]
+pub enum Node<'a> {
+    /// A nested collection
+    Collection(CollectionNode<'a>),
+
+    /// An Input, Output or Feature item
+    Item(&'a ReportDescriptorItem),
+}
+
+/// A top-level Application collection, one function of a (composite) device
+///
+/// Host HID stacks expose each top-level Application collection as a separate
+/// device, together with the reports declared inside it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ApplicationCollection<'a> {
+    item: &'a ReportDescriptorItem,
+    usage_page: UsagePage,
+    usage: Usage,
+    input_report_ids: BTreeSet<u8>,
+    output_report_ids: BTreeSet<u8>,
+    feature_report_ids: BTreeSet<u8>,
+}
+
+/// Depth-first iterator over collections, see [`CollectionTree::iter`]
+pub struct Collections<'t, 'a> {
+    stack: Vec<&'t CollectionNode<'a>>,
+}
+
+impl ReportDescriptor {
+    /// Get the collections of the descriptor as a tree
+    ///
+    /// The usage of a collection is the first Usage item before it. Unbalanced
+    /// descriptors are tolerated: a superfluous End Collection is ignored and
+    /// collections that are never closed end with the descriptor.
+    ///
+    /// # Example
+    /// ```
+    /// use hid_tools::report_builder::ReportDescriptorBuilder;
+    /// use hid_tools::report_descriptor::Collection;
+    /// use hid_tools::usage_table::{Usage, UsagePage};
+    /// use hid_tools::usage_table::generic_desktop::GenericDesktopControlsUsage;
+    ///
+    /// let descriptor = ReportDescriptorBuilder::new()
+    ///     .usage_page(UsagePage::GenericDesktopControls)
+    ///     .usage(GenericDesktopControlsUsage::Mouse)
+    ///     .collection(Collection::Application)
+    ///     .usage(GenericDesktopControlsUsage::Pointer)
+    ///     .collection(Collection::Physical)
+    ///     .end_collection()
+    ///     .end_collection()
+    ///     .build();
+    ///
+    /// let tree = descriptor.collection_tree();
+    /// let pointer = tree
+    ///     .find(&Usage::GenericDesktopControls(GenericDesktopControlsUsage::Mouse))
+    ///     .and_then(|mouse| mouse.find(&Usage::GenericDesktopControls(GenericDesktopControlsUsage::Pointer)))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(pointer.collection(), &Collection::Physical);
+    /// ```
+    pub fn collection_tree(&self) -> CollectionTree<'_> {
+        let mut root: Vec<Node> = Vec::new();
+        let mut open: Vec<CollectionNode> = Vec::new();
+
+        walk_main_items(self, |item, state| match &item.kind {
+            ItemType::Main(MainType::Collection) => {
+                let (usage_page, usage) = state.collection_usage();
+
+                open.push(CollectionNode {
+                    item,
+                    // An empty payload has the value of the Physical collection (0x00)
+                    collection: item.collection().unwrap_or(Collection::Physical),
+                    usage_page,
+                    usage,
+                    children: Vec::new(),
+                });
+            }
+            ItemType::Main(MainType::EndCollection) => {
+                if let Some(node) = open.pop() {
+                    attach(&mut root, &mut open, Node::Collection(node));
+                }
+            }
+            _ => attach(&mut root, &mut open, Node::Item(item)),
+        });
+
+        // Close collections without End Collection
+        while let Some(node) = open.pop() {
+            attach(&mut root, &mut open, Node::Collection(node));
+        }
+
+        CollectionTree { children: root }
+    }
+
+    /// Get the top-level Application collections with the Report IDs of their reports
+    ///
+    /// Reports of nested collections belong to the enclosing Application collection.
+    /// A descriptor without Report ID items has empty Report ID sets.
+    ///
+    /// # Example
+    /// ```
+    /// use hid_tools::report_builder::ReportDescriptorBuilder;
+    /// use hid_tools::report_descriptor::Collection;
+    /// use hid_tools::usage_table::UsagePage;
+    /// use hid_tools::usage_table::consumer::ConsumerUsage;
+    /// use hid_tools::usage_table::generic_desktop::GenericDesktopControlsUsage;
+    ///
+    /// let descriptor = ReportDescriptorBuilder::new()
+    ///     .usage_page(UsagePage::GenericDesktopControls)
+    ///     .usage(GenericDesktopControlsUsage::Keyboard)
+    ///     .collection(Collection::Application)
+    ///     .report_id(1)
+    ///     .report_size(8)
+    ///     .report_count(1)
+    ///     .input(0x00)
+    ///     .output(0x02)
+    ///     .end_collection()
+    ///     .usage_page(UsagePage::Consumer)
+    ///     .usage(ConsumerUsage::ConsumerControl)
+    ///     .collection(Collection::Application)
+    ///     .report_id(3)
+    ///     .input(0x00)
+    ///     .end_collection()
+    ///     .build();
+    ///
+    /// let applications = descriptor.application_collections();
+    /// assert_eq!(applications.len(), 2);
+    /// assert_eq!(applications[0].output_report_ids().iter().collect::<Vec<_>>(), vec![&1]);
+    /// assert_eq!(applications[1].usage_page(), &UsagePage::Consumer);
+    /// ```
+    pub fn application_collections(&self) -> Vec<ApplicationCollection<'_>> {
+        let mut applications: Vec<ApplicationCollection> = Vec::new();
+        // Whether the outermost open collection is an Application collection
+        let mut in_application = false;
+        let mut depth = 0usize;
+
+        walk_main_items(self, |item, state| match &item.kind {
+            ItemType::Main(MainType::Collection) => {
+                if depth == 0 {
+                    in_application = item.collection() == Some(Collection::Application);
+                }
+
+                if depth == 0 && in_application {
+                    let (usage_page, usage) = state.collection_usage();
+
+                    applications.push(ApplicationCollection {
+                        item,
+                        usage_page,
+                        usage,
+                        input_report_ids: BTreeSet::new(),
+                        output_report_ids: BTreeSet::new(),
+                        feature_report_ids: BTreeSet::new(),
+                    });
+                }
+                depth += 1;
+            }
+            ItemType::Main(MainType::EndCollection) => {
+                depth = depth.saturating_sub(1);
+            }
+            ItemType::Main(main_type) => {
+                if let (true, Some(application), Some(id)) = (
+                    depth > 0 && in_application,
+                    applications.last_mut(),
+                    state.globals.report_id(),
+                ) {
+                    match main_type {
+                        MainType::Input => application.input_report_ids.insert(id),
+                        MainType::Output => application.output_report_ids.insert(id),
+                        MainType::Feature => application.feature_report_ids.insert(id),
+                        _ => false,
+                    };
+                }
+            }
+            _ => {}
+        });
+
+        applications
+    }
+}
+
+/// The global items and the first Usage in effect at a main item
+struct WalkState<'a> {
+    globals: GlobalItemTracker,
+    usage: Option<&'a ReportDescriptorItem>,
+}
+
+impl WalkState<'_> {
+    /// Get the Usage Page and usage of a collection opened at this point
+    fn collection_usage(&self) -> (UsagePage, Usage) {
+        let usage_page = self.globals.usage_page().cloned().unwrap_or_default();
+        let (page, id) = self
+            .usage
+            .and_then(|usage| usage.usage_page_and_id(&usage_page))
+            .unwrap_or((usage_page, 0));
+
+        (page.clone(), Usage::from((&page, id)))
+    }
+}
+
+/// Call `visit` for every main item, with the state in effect at that item
+///
+/// Invalid global items are skipped and a Pop without Push keeps the current
+/// state, so any descriptor can be walked.
+fn walk_main_items<'a>(
+    descriptor: &'a ReportDescriptor,
+    mut visit: impl FnMut(&'a ReportDescriptorItem, &WalkState<'a>),
+) {
+    let mut state = WalkState {
+        globals: GlobalItemTracker::default(),
+        usage: None,
+    };
+
+    for item in descriptor.items() {
+        match &item.kind {
+            ItemType::Global(_) => {
+                let _ = state.globals.apply(item);
+            }
+            ItemType::Local(LocalType::Usage) => {
+                state.usage = state.usage.or(Some(item));
+            }
+            ItemType::Main(_) => {
+                visit(item, &state);
+                // Local items only apply to the next main item
+                state.usage = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Add a node to the innermost open collection, or to the root
+fn attach<'a>(root: &mut Vec<Node<'a>>, open: &mut [CollectionNode<'a>], node: Node<'a>) {
+    match open.last_mut() {
+        Some(parent) => parent.children.push(node),
+        None => root.push(node),
+    }
+}
+
+impl<'a> CollectionTree<'a> {
+    /// Get the top-level nodes
+    pub fn children(&self) -> &Vec<Node<'a>> {
+        &self.children
+    }
+
+    /// Iterate over the top-level collections
+    pub fn collections(&self) -> impl Iterator<Item = &CollectionNode<'a>> {
+        collections(&self.children)
+    }
+
+    /// Iterate over all collections, depth-first
+    pub fn iter(&self) -> Collections<'_, 'a> {
+        Collections::new(&self.children)
+    }
+
+    /// Find the first collection (depth-first) with the given usage
+    pub fn find(&self, usage: &Usage) -> Option<&CollectionNode<'a>> {
+        self.iter().find(|node| &node.usage == usage)
+    }
+}
+
+impl<'a> CollectionNode<'a> {
+    /// Get the Collection item
+    pub fn item(&self) -> &'a ReportDescriptorItem {
+        self.item
+    }
+
+    /// Get the type of the collection
+    pub fn collection(&self) -> &Collection {
+        &self.collection
+    }
+
+    /// Get the Usage Page of the collection usage
+    pub fn usage_page(&self) -> &UsagePage {
+        &self.usage_page
+    }
+
+    /// Get the usage of the collection
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// Get the nested collections and main items, in order of appearance
+    pub fn children(&self) -> &Vec<Node<'a>> {
+        &self.children
+    }
+
+    /// Iterate over the directly nested collections
+    pub fn collections(&self) -> impl Iterator<Item = &CollectionNode<'a>> {
+        collections(&self.children)
+    }
+
+    /// Iterate over the Input, Output and Feature items directly in this collection
+    pub fn main_items(&self) -> impl Iterator<Item = &'a ReportDescriptorItem> + '_ {
+        self.children.iter().filter_map(|child| match child {
+            Node::Item(item) => Some(*item),
+            Node::Collection(_) => None,
+        })
+    }
+
+    /// Iterate over all nested collections, depth-first
+    pub fn iter(&self) -> Collections<'_, 'a> {
+        Collections::new(&self.children)
+    }
+
+    /// Find the first nested collection (depth-first) with the given usage
+    pub fn find(&self, usage: &Usage) -> Option<&CollectionNode<'a>> {
+        self.iter().find(|node| &node.usage == usage)
+    }
+}
+
+impl<'a> ApplicationCollection<'a> {
+    /// Get the Collection item
+    pub fn item(&self) -> &'a ReportDescriptorItem {
+        self.item
+    }
+
+    /// Get the Usage Page of the collection usage
+    pub fn usage_page(&self) -> &UsagePage {
+        &self.usage_page
+    }
+
+    /// Get the usage of the collection
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// Get the Report IDs of the Input reports in this collection
+    pub fn input_report_ids(&self) -> &BTreeSet<u8> {
+        &self.input_report_ids
+    }
+
+    /// Get the Report IDs of the Output reports in this collection
+    pub fn output_report_ids(&self) -> &BTreeSet<u8> {
+        &self.output_report_ids
+    }
+
+    /// Get the Report IDs of the Feature reports in this collection
+    pub fn feature_report_ids(&self) -> &BTreeSet<u8> {
+        &self.feature_report_ids
+    }
+
+    /// Get all Report IDs used in this collection, whatever the report type
+    pub fn report_ids(&self) -> BTreeSet<u8> {
+        self.input_report_ids
+            .iter()
+            .chain(self.output_report_ids.iter())
+            .chain(self.feature_report_ids.iter())
+            .copied()
+            .collect()
+    }
+}
+
+/// Filter the collections from a list of nodes
+fn collections<'t, 'a>(nodes: &'t [Node<'a>]) -> impl Iterator<Item = &'t CollectionNode<'a>> {
+    nodes.iter().filter_map(|node| match node {
+        Node::Collection(collection) => Some(collection),
+        Node::Item(_) => None,
+    })
+}
+
+impl<'t, 'a> Collections<'t, 'a> {
+    fn new(nodes: &'t [Node<'a>]) -> Self {
+        let mut stack: Vec<&CollectionNode> = collections(nodes).collect();
+        stack.reverse();
+
+        Collections { stack }
+    }
+}
+
+impl<'t, 'a> Iterator for Collections<'t, 'a> {
+    type Item = &'t CollectionNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+
+        let first = self.stack.len();
+        self.stack.extend(node.collections());
+        self.stack[first..].reverse();
+
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report_builder::ReportDescriptorBuilder;
+    use crate::report_descriptor::data::SizedPayload;
+    use crate::usage_table::consumer::ConsumerUsage;
+    use crate::usage_table::generic_desktop::GenericDesktopControlsUsage;
+
+    fn mouse_and_consumer() -> ReportDescriptor {
+        ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .usage(GenericDesktopControlsUsage::Mouse)
+            .collection(Collection::Application)
+            .usage(GenericDesktopControlsUsage::Pointer)
+            .collection(Collection::Physical)
+            .usage(GenericDesktopControlsUsage::X)
+            .report_size(8)
+            .report_count(1)
+            .input(0x06)
+            .end_collection()
+            .end_collection()
+            .usage_page(UsagePage::Consumer)
+            .usage(ConsumerUsage::ConsumerControl)
+            .collection(Collection::Application)
+            .input(0x00)
+            .end_collection()
+            .build()
+    }
+
+    #[test]
+    fn nested_collections() {
+        let descriptor = mouse_and_consumer();
+        let tree = descriptor.collection_tree();
+
+        let usages: Vec<&Usage> = tree.iter().map(|node| node.usage()).collect();
+        assert_eq!(
+            usages,
+            vec![
+                &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Mouse),
+                &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Pointer),
+                &Usage::Consumer(ConsumerUsage::ConsumerControl),
+            ]
+        );
+        assert_eq!(tree.collections().count(), 2);
+    }
+
+    #[test]
+    fn main_items_in_collection() {
+        let descriptor = mouse_and_consumer();
+        let tree = descriptor.collection_tree();
+
+        let pointer = tree
+            .find(&Usage::GenericDesktopControls(
+                GenericDesktopControlsUsage::Pointer,
+            ))
+            .unwrap();
+
+        assert_eq!(pointer.main_items().count(), 1);
+        assert_eq!(pointer.usage_page(), &UsagePage::GenericDesktopControls);
+        assert!(pointer.main_items().all(|item| item.is_input()));
+    }
+
+    #[test]
+    fn unbalanced_collections_are_tolerated() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .end_collection()
+            .collection(Collection::Application)
+            .collection(Collection::Logical)
+            .build();
+        let tree = descriptor.collection_tree();
+
+        assert_eq!(tree.collections().count(), 1);
+        assert_eq!(tree.iter().count(), 2);
+    }
+
+    #[test]
+    fn pop_restores_usage_page_of_collection() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .usage_page(UsagePage::GenericDesktopControls)
+            .push(SizedPayload::Empty)
+            .usage_page(UsagePage::Consumer)
+            .pop(SizedPayload::Empty)
+            .usage(GenericDesktopControlsUsage::Keyboard)
+            .collection(Collection::Application)
+            .pop(SizedPayload::Empty) // without Push, keeps the state
+            .usage(GenericDesktopControlsUsage::Keypad)
+            .collection(Collection::Logical)
+            .end_collection()
+            .end_collection()
+            .build();
+        let tree = descriptor.collection_tree();
+
+        let usages: Vec<&Usage> = tree.iter().map(|node| node.usage()).collect();
+        assert_eq!(
+            usages,
+            vec![
+                &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Keyboard),
+                &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Keypad),
+            ]
+        );
+    }
+
+    #[test]
+    fn application_collections_without_report_id() {
+        let descriptor = mouse_and_consumer();
+        let applications = descriptor.application_collections();
+
+        assert_eq!(applications.len(), 2);
+        assert_eq!(
+            applications[0].usage(),
+            &Usage::GenericDesktopControls(GenericDesktopControlsUsage::Mouse)
+        );
+        assert!(applications[0].report_ids().is_empty());
+    }
+
+    #[test]
+    fn application_collections_skip_other_top_level_collections() {
+        let descriptor = ReportDescriptorBuilder::new()
+            .report_id(1)
+            .collection(Collection::Logical)
+            .input(0x00)
+            .end_collection()
+            .usage_page(UsagePage::VendorDefined(0xff00))
+            .usage::<u16>(0x01)
+            .collection(Collection::Application)
+            .push(SizedPayload::Empty)
+            .report_id(2)
+            .feature(0x02)
+            .pop(SizedPayload::Empty)
+            .feature(0x02)
+            .end_collection()
+            .build();
+        let applications = descriptor.application_collections();
+
+        assert_eq!(applications.len(), 1);
+        assert_eq!(
+            applications[0].usage_page(),
+            &UsagePage::VendorDefined(0xff00)
+        );
+        assert!(applications[0].input_report_ids().is_empty());
+        assert_eq!(
+            applications[0].feature_report_ids(),
+            &BTreeSet::from([1, 2])
+        );
+    }
+}
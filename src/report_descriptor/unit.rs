@@ -0,0 +1,306 @@
+/// The system of units used by the Unit item
+///
+/// For definition in the HID protocol see
+/// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 37
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum UnitSystem {
+    /// No unit
+    #[default]
+    None,
+
+    /// SI linear units (centimetre, gram, second, kelvin, ampere, candela)
+    SILinear,
+
+    /// SI rotation units (radians instead of centimetres)
+    SIRotation,
+
+    /// English linear units (inch, slug, second, fahrenheit, ampere, candela)
+    EnglishLinear,
+
+    /// English rotation units (degrees instead of inches)
+    EnglishRotation,
+
+    /// Reserved for future use
+    Reserved(u8),
+
+    /// Vendor defined unit system
+    VendorDefined,
+}
+
+impl From<u8> for UnitSystem {
+    fn from(value: u8) -> Self {
+        match value {
+            0x0 => UnitSystem::None,
+            0x1 => UnitSystem::SILinear,
+            0x2 => UnitSystem::SIRotation,
+            0x3 => UnitSystem::EnglishLinear,
+            0x4 => UnitSystem::EnglishRotation,
+            0xf => UnitSystem::VendorDefined,
+            i => UnitSystem::Reserved(i),
+        }
+    }
+}
+
+impl From<UnitSystem> for u8 {
+    fn from(value: UnitSystem) -> Self {
+        match value {
+            UnitSystem::None => 0x0,
+            UnitSystem::SILinear => 0x1,
+            UnitSystem::SIRotation => 0x2,
+            UnitSystem::EnglishLinear => 0x3,
+            UnitSystem::EnglishRotation => 0x4,
+            UnitSystem::VendorDefined => 0xf,
+            UnitSystem::Reserved(i) => i,
+        }
+    }
+}
+
+/// A decoded Unit item
+///
+/// The payload of the Unit item is split in nibbles. The first nibble is the unit
+/// system, the following six nibbles are the (signed) exponents of length, mass, time,
+/// temperature, current and luminous intensity. For example, a velocity in cm/s has
+/// a length exponent of 1 and a time exponent of -1. The last nibble is reserved, it
+/// is kept so the payload encodes back unchanged.
+///
+/// For definition in the HID protocol see
+/// <https://www.usb.org/sites/default/files/hid1_11.pdf> - page 37
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Unit {
+    system: UnitSystem,
+    length: i8,
+    mass: i8,
+    time: i8,
+    temperature: i8,
+    current: i8,
+    luminous_intensity: i8,
+    reserved: u8, // The last nibble, kept as is
+}
+
+impl Unit {
+    /// Create a new Unit in the given system with all exponents set to zero
+    ///
+    /// # Example
+    /// ```
+    /// use hid_tools::report_descriptor::unit::{Unit, UnitSystem};
+    ///
+    /// // Acceleration in cm·s⁻²
+    /// let unit = Unit::new(UnitSystem::SILinear).with_length(1).with_time(-2);
+    /// assert_eq!(u32::from(unit), 0xe011);
+    /// ```
+    pub fn new(system: UnitSystem) -> Self {
+        Unit {
+            system,
+            ..Default::default()
+        }
+    }
+
+    /// Set the exponent of length
+    ///
+    /// # Panics
+    /// If the exponent is outside -8 to 7, the range of a nibble.
+    pub fn with_length(mut self, exponent: i8) -> Self {
+        self.length = checked_exponent(exponent);
+        self
+    }
+
+    /// Set the exponent of mass
+    ///
+    /// # Panics
+    /// If the exponent is outside -8 to 7, the range of a nibble.
+    pub fn with_mass(mut self, exponent: i8) -> Self {
+        self.mass = checked_exponent(exponent);
+        self
+    }
+
+    /// Set the exponent of time
+    ///
+    /// # Panics
+    /// If the exponent is outside -8 to 7, the range of a nibble.
+    pub fn with_time(mut self, exponent: i8) -> Self {
+        self.time = checked_exponent(exponent);
+        self
+    }
+
+    /// Set the exponent of temperature
+    ///
+    /// # Panics
+    /// If the exponent is outside -8 to 7, the range of a nibble.
+    pub fn with_temperature(mut self, exponent: i8) -> Self {
+        self.temperature = checked_exponent(exponent);
+        self
+    }
+
+    /// Set the exponent of current
+    ///
+    /// # Panics
+    /// If the exponent is outside -8 to 7, the range of a nibble.
+    pub fn with_current(mut self, exponent: i8) -> Self {
+        self.current = checked_exponent(exponent);
+        self
+    }
+
+    /// Set the exponent of luminous intensity
+    ///
+    /// # Panics
+    /// If the exponent is outside -8 to 7, the range of a nibble.
+    pub fn with_luminous_intensity(mut self, exponent: i8) -> Self {
+        self.luminous_intensity = checked_exponent(exponent);
+        self
+    }
+
+    /// Get the unit system
+    pub fn system(&self) -> UnitSystem {
+        self.system
+    }
+
+    /// Get the exponent of length (cm, rad, in or deg depending on the system)
+    pub fn length(&self) -> i8 {
+        self.length
+    }
+
+    /// Get the exponent of mass (g or slug)
+    pub fn mass(&self) -> i8 {
+        self.mass
+    }
+
+    /// Get the exponent of time (s)
+    pub fn time(&self) -> i8 {
+        self.time
+    }
+
+    /// Get the exponent of temperature (K or °F)
+    pub fn temperature(&self) -> i8 {
+        self.temperature
+    }
+
+    /// Get the exponent of current (A)
+    pub fn current(&self) -> i8 {
+        self.current
+    }
+
+    /// Get the exponent of luminous intensity (cd)
+    pub fn luminous_intensity(&self) -> i8 {
+        self.luminous_intensity
+    }
+
+    /// Returns true if the unit has no system or all exponents are zero
+    pub fn is_none(&self) -> bool {
+        self.system == UnitSystem::None || self.exponents().iter().all(|e| *e == 0)
+    }
+
+    /// Get all exponents, in order of the nibbles in the payload
+    pub(crate) fn exponents(&self) -> [i8; 6] {
+        [
+            self.length,
+            self.mass,
+            self.time,
+            self.temperature,
+            self.current,
+            self.luminous_intensity,
+        ]
+    }
+}
+
+impl From<u32> for Unit {
+    fn from(value: u32) -> Self {
+        let nibble = |n: u32| ((value >> (n * 4)) & 0xf) as u8;
+
+        Unit {
+            system: UnitSystem::from(nibble(0)),
+            length: signed_nibble(nibble(1)),
+            mass: signed_nibble(nibble(2)),
+            time: signed_nibble(nibble(3)),
+            temperature: signed_nibble(nibble(4)),
+            current: signed_nibble(nibble(5)),
+            luminous_intensity: signed_nibble(nibble(6)),
+            reserved: nibble(7),
+        }
+    }
+}
+
+impl From<Unit> for u32 {
+    fn from(value: Unit) -> Self {
+        value
+            .exponents()
+            .iter()
+            .enumerate()
+            .fold(u32::from(u8::from(value.system)) & 0xf, |acc, (i, e)| {
+                acc | (((*e as u8 as u32) & 0xf) << ((i + 1) * 4))
+            })
+            | (u32::from(value.reserved) << 28)
+    }
+}
+
+/// Check that an exponent fits in a nibble
+fn checked_exponent(exponent: i8) -> i8 {
+    assert!(
+        (-8..=7).contains(&exponent),
+        "unit exponent {} is outside -8 to 7",
+        exponent
+    );
+    exponent
+}
+
+/// Interpret the lower four bits as a two's complement value (-8 to 7)
+pub(crate) fn signed_nibble(value: u8) -> i8 {
+    (((value & 0xf) << 4) as i8) >> 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_nibbles() {
+        assert_eq!(signed_nibble(0x7), 7);
+        assert_eq!(signed_nibble(0x8), -8);
+        assert_eq!(signed_nibble(0xe), -2);
+    }
+
+    #[test]
+    fn decode_acceleration() {
+        // cm·s⁻² in SI Linear
+        let unit = Unit::from(0xe011);
+
+        assert_eq!(unit.system(), UnitSystem::SILinear);
+        assert_eq!(unit.length(), 1);
+        assert_eq!(unit.time(), -2);
+        assert_eq!(unit.mass(), 0);
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        for raw in [
+            0x0u32,
+            0x11,
+            0xe011,
+            0xf0d121,
+            0x0101_0001,
+            0x12,
+            0x7000_0011,
+        ] {
+            assert_eq!(u32::from(Unit::from(raw)), raw);
+        }
+    }
+
+    #[test]
+    fn encode_negative_exponents() {
+        let unit = Unit::new(UnitSystem::EnglishRotation)
+            .with_length(-8)
+            .with_current(-1);
+        assert_eq!(u32::from(unit), 0x00f0_0084);
+    }
+
+    #[test]
+    #[should_panic(expected = "unit exponent 9 is outside -8 to 7")]
+    fn exponent_out_of_range_panics() {
+        let _ = Unit::new(UnitSystem::SILinear).with_length(9);
+    }
+
+    #[test]
+    fn decode_no_unit() {
+        assert!(Unit::from(0).is_none());
+        assert_eq!(Unit::from(0).system(), UnitSystem::None);
+    }
+}
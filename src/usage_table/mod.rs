@@ -18,8 +18,9 @@ pub trait UsageId {
 ///
 /// For definition in the HID protocol see:
 /// <https://usb.org/sites/default/files/hut1_3_0.pdf> - page 17
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum UsagePage {
+    #[default]
     Undefined,
     GenericDesktopControls,
     SimulationControls,
@@ -59,8 +60,9 @@ pub enum UsagePage {
     Reserved(u16),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Default)]
 pub enum Usage {
+    #[default]
     Undefined,
     GenericDesktopControls(GenericDesktopControlsUsage),
     SimulationControls(u16),
@@ -286,18 +288,6 @@ impl From<(&UsagePage, u16)> for Usage {
     }
 }
 
-impl Default for UsagePage {
-    fn default() -> Self {
-        UsagePage::Undefined
-    }
-}
-
-impl Default for Usage {
-    fn default() -> Self {
-        Usage::Undefined
-    }
-}
-
 impl UsageId for Usage {
     fn usage_id(self) -> u16 {
         self.into()
@@ -1,5 +1,6 @@
-use hid_tools::report::{expected_input_reports, parse_raw_input_report};
+use hid_tools::report::{expected_input_reports, expected_output_reports, parse_raw_input_report};
 use hid_tools::report_descriptor::parse;
+use hid_tools::usage_table::UsagePage;
 
 #[test]
 fn main() {
@@ -146,4 +147,74 @@ Keyboard - Keyboard 2 and @
 Consumer - Volume Increment
 "
     );
+
+    // Test mouse report: buttons 1 & 3 pressed, moved left and scrolled down
+    let report: Vec<u8> = vec![2, 0x05, 0x00, 0xfb, 0x3f, 0x00, 0xff, 0x00];
+    let parsed_report = parse_raw_input_report(&report, &expected).unwrap();
+
+    assert_eq!(
+        format!("{}", parsed_report),
+        "ReportId(2)
+Button - 1(1)
+Button - 2(0)
+Button - 3(1)
+Button - 4(0)
+Button - 5(0)
+Button - 6(0)
+Button - 7(0)
+Button - 8(0)
+Button - 9(0)
+Button - 10(0)
+Button - 11(0)
+Button - 12(0)
+Button - 13(0)
+Button - 14(0)
+Button - 15(0)
+Button - 16(0)
+Generic Desktop Controls - X(-5)
+Generic Desktop Controls - Y(3)
+Generic Desktop Controls - Wheel(-1)
+Consumer - AC Pan(0)
+"
+    );
+
+    // Keyboard LEDs and the vendor defined output report
+    let expected = expected_output_reports(&report_descriptor).unwrap();
+    let sizes: Vec<(Option<u8>, usize)> = expected
+        .reports()
+        .iter()
+        .map(|r| (r.report_id(), r.size()))
+        .collect();
+
+    assert_eq!(sizes, vec![(Some(1), 16), (Some(0x11), 160)]);
+
+    // Test the functions of the composite device
+    let functions: Vec<(UsagePage, Vec<u8>, Vec<u8>)> = report_descriptor
+        .application_collections()
+        .iter()
+        .map(|a| {
+            (
+                a.usage_page().clone(),
+                a.input_report_ids().iter().copied().collect(),
+                a.output_report_ids().iter().copied().collect(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        functions,
+        vec![
+            (UsagePage::GenericDesktopControls, vec![1], vec![1]),
+            (UsagePage::GenericDesktopControls, vec![2], vec![]),
+            (UsagePage::Consumer, vec![3], vec![]),
+            (UsagePage::VendorDefined(0xff43), vec![0x11], vec![0x11]),
+        ]
+    );
+
+    // Test the minimised descriptor: repeated globals are removed and payloads shrunk
+    let minimised = report_descriptor.minimise().unwrap();
+    assert_eq!(minimised.bytes().len(), 170);
+
+    // Test the descriptor follows the rules of the HID specification
+    assert_eq!(report_descriptor.lint(), vec![]);
 }